@@ -0,0 +1,887 @@
+// Client for the HN Search API, see https://hn.algolia.com/api
+// The Firebase API has no search, so full-text search goes through Algolia
+// Search isn't wired up to the UI yet, until then parts of the client are only used in tests
+#![cfg_attr(not(test), allow(dead_code))]
+use crate::backend::HnBackend;
+use crate::constants::{PAGE_SIZE, PARALLEL_REQUESTS, REQUEST_TIMEOUT_SECS};
+use crate::hn_client::{BatchResult, CommentTree, CommentTreeLimits, HnError};
+use crate::stores::data::{AnyItem, Comment, Job, Poll, PollOption, Post, Story, Submission, User};
+use crate::stores::view::StoryListType;
+use futures::future::{BoxFuture, FutureExt};
+use futures::{stream, StreamExt};
+use serde::de::DeserializeOwned;
+use serde::{de, Deserialize, Deserializer};
+use std::fmt;
+use std::time::Duration;
+
+const DEFAULT_SEARCH_URL: &str = "https://hn.algolia.com/api/v1";
+// Number of ids in story lists and user submissions when used as a backend
+const LIST_SIZE: u32 = 100;
+
+// Hits are filtered down to items with all of the given tags
+#[derive(Clone, Debug, PartialEq)]
+pub enum Tag {
+    Story,
+    Comment,
+    AskHn,
+    ShowHn,
+    Job,
+    // Stories currently on the front page
+    FrontPage,
+    Author(String),
+}
+
+impl fmt::Display for Tag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Tag::Story => write!(f, "story"),
+            Tag::Comment => write!(f, "comment"),
+            Tag::AskHn => write!(f, "ask_hn"),
+            Tag::ShowHn => write!(f, "show_hn"),
+            Tag::Job => write!(f, "job"),
+            Tag::FrontPage => write!(f, "front_page"),
+            Tag::Author(user) => write!(f, "author_{}", user),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NumericField {
+    Points,
+    NumComments,
+    // Unix timestamp the item was created at
+    CreatedAt,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Comparison {
+    Lt,
+    Le,
+    Eq,
+    Ge,
+    Gt,
+}
+
+// e.g. `points > 100` is `NumericFilter { field: Points, comparison: Gt, value: 100 }`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NumericFilter {
+    pub field: NumericField,
+    pub comparison: Comparison,
+    pub value: i64,
+}
+
+impl fmt::Display for NumericFilter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let field = match self.field {
+            NumericField::Points => "points",
+            NumericField::NumComments => "num_comments",
+            NumericField::CreatedAt => "created_at_i",
+        };
+        let comparison = match self.comparison {
+            Comparison::Lt => "<",
+            Comparison::Le => "<=",
+            Comparison::Eq => "=",
+            Comparison::Ge => ">=",
+            Comparison::Gt => ">",
+        };
+        write!(f, "{}{}{}", field, comparison, self.value)
+    }
+}
+
+pub struct SearchQuery {
+    pub query: String,
+    pub tags: Vec<Tag>,
+    pub numeric_filters: Vec<NumericFilter>,
+    // Pages start from 0
+    pub page: u32,
+    pub hits_per_page: u32,
+}
+
+impl Default for SearchQuery {
+    fn default() -> SearchQuery {
+        SearchQuery {
+            query: String::new(),
+            tags: Vec::new(),
+            numeric_filters: Vec::new(),
+            page: 0,
+            hits_per_page: PAGE_SIZE as u32,
+        }
+    }
+}
+
+impl SearchQuery {
+    fn to_params(&self) -> Vec<(&'static str, String)> {
+        let mut params = vec![
+            ("query", self.query.clone()),
+            ("page", self.page.to_string()),
+            ("hitsPerPage", self.hits_per_page.to_string()),
+        ];
+        if !self.tags.is_empty() {
+            let tags: Vec<String> = self.tags.iter().map(|tag| tag.to_string()).collect();
+            params.push(("tags", tags.join(",")));
+        }
+        if !self.numeric_filters.is_empty() {
+            let filters: Vec<String> = self
+                .numeric_filters
+                .iter()
+                .map(|filter| filter.to_string())
+                .collect();
+            params.push(("numericFilters", filters.join(",")));
+        }
+        params
+    }
+}
+
+// Hits that could not be mapped to a story or comment are listed in `hits.failed`
+pub struct SearchResults {
+    pub hits: BatchResult<Submission>,
+    pub page: u32,
+    pub pages: u32,
+    // Number of hits across all pages
+    pub total: u32,
+}
+
+// Defined by https://hn.algolia.com/api - the fields depend on the type of item
+#[derive(Debug, Deserialize)]
+struct AlgoliaHit {
+    #[serde(rename = "objectID", deserialize_with = "deserialize_id")]
+    id: u32,
+    #[serde(rename = "_tags", default)]
+    tags: Vec<String>,
+    author: String,
+    created_at_i: u32,
+    title: Option<String>,
+    url: Option<String>,
+    story_text: Option<String>,
+    comment_text: Option<String>,
+    points: Option<u16>,
+    num_comments: Option<u32>,
+    parent_id: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AlgoliaResponse {
+    hits: Vec<AlgoliaHit>,
+    nb_hits: u32,
+    page: u32,
+    nb_pages: u32,
+}
+
+// An item from /items/{id} along with all of its replies, nested in `children`
+// Deleted comments have no `author` or `text`
+#[derive(Debug, Deserialize)]
+struct AlgoliaItem {
+    id: u32,
+    #[serde(rename = "type")]
+    item_type: String,
+    author: Option<String>,
+    title: Option<String>,
+    url: Option<String>,
+    text: Option<String>,
+    points: Option<u16>,
+    created_at_i: u32,
+    parent_id: Option<u32>,
+    #[serde(default)]
+    children: Vec<AlgoliaItem>,
+    // The poll options of a poll
+    #[serde(default)]
+    options: Vec<AlgoliaItem>,
+}
+
+impl AlgoliaItem {
+    fn is_comment(&self) -> bool {
+        self.item_type == "comment"
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct AlgoliaUser {
+    username: String,
+    about: Option<String>,
+    karma: i32,
+    #[serde(default)]
+    created_at_i: u32,
+}
+
+// Ids are sent as strings
+fn deserialize_id<'de, D>(deserializer: D) -> Result<u32, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let id = String::deserialize(deserializer)?;
+    id.parse().map_err(de::Error::custom)
+}
+
+// Coerse hit -> public facing Post / Job / Comment struct
+// Search hits do not list replies, so `children` is left empty
+fn to_submission(hit: AlgoliaHit) -> Result<Submission, HnError> {
+    let has_tag = |tag: &str| hit.tags.iter().any(|hit_tag| hit_tag == tag);
+    if has_tag("comment") {
+        if let Some(parent) = hit.parent_id {
+            return Ok(Submission::Comment(Comment {
+                id: hit.id,
+                by: Some(hit.author),
+                children: Vec::new(),
+                parent,
+                text: hit.comment_text,
+                time: hit.created_at_i,
+                dead: false,
+                deleted: false,
+            }));
+        }
+    } else if has_tag("job") {
+        if let Some(title) = hit.title {
+            return Ok(Submission::Story(Story::Job(Job {
+                id: hit.id,
+                by: hit.author,
+                title,
+                time: hit.created_at_i,
+                url: hit.url,
+                text: hit.story_text,
+                dead: false,
+                deleted: false,
+            })));
+        }
+    } else if has_tag("story") {
+        if let Some(title) = hit.title {
+            return Ok(Submission::Story(Story::Post(Post {
+                id: hit.id,
+                by: hit.author,
+                children: Vec::new(),
+                title,
+                time: hit.created_at_i,
+                url: hit.url,
+                text: hit.story_text,
+                descendants: hit.num_comments.unwrap_or(0),
+                score: hit.points.unwrap_or(0),
+                dead: false,
+                deleted: false,
+            })));
+        }
+    }
+    Err(HnError::UnexpectedItemType {
+        id: hit.id,
+        expected: "story or comment",
+    })
+}
+
+// Flattens the comments in `children` into `comments`, keeping the links between them
+fn collect_comments(children: Vec<AlgoliaItem>, parent: u32, comments: &mut Vec<Comment>) {
+    for child in children {
+        if !child.is_comment() {
+            continue;
+        }
+        let child_ids = comment_ids(&child.children);
+        comments.push(Comment {
+            id: child.id,
+            deleted: child.author.is_none() && child.text.is_none(),
+            by: child.author,
+            children: child_ids,
+            parent: child.parent_id.unwrap_or(parent),
+            text: child.text,
+            time: child.created_at_i,
+            dead: false,
+        });
+        collect_comments(child.children, child.id, comments);
+    }
+}
+
+fn comment_ids(children: &[AlgoliaItem]) -> Vec<u32> {
+    children
+        .iter()
+        .filter(|child| child.is_comment())
+        .map(|child| child.id)
+        .collect()
+}
+
+fn count_comments(children: &[AlgoliaItem]) -> u32 {
+    children
+        .iter()
+        .filter(|child| child.is_comment())
+        .map(|child| 1 + count_comments(&child.children))
+        .sum()
+}
+
+// Coerse item -> public facing Post / Job / Poll / Comment struct
+fn item_to_submission(item: AlgoliaItem) -> Result<Submission, HnError> {
+    let children = comment_ids(&item.children);
+    let descendants = count_comments(&item.children);
+    match item.item_type.as_str() {
+        "story" => Ok(Submission::Story(Story::Post(Post {
+            id: item.id,
+            deleted: item.author.is_none(),
+            by: item.author.unwrap_or_default(),
+            children,
+            title: item.title.unwrap_or_default(),
+            time: item.created_at_i,
+            url: item.url,
+            text: item.text,
+            descendants,
+            score: item.points.unwrap_or(0),
+            dead: false,
+        }))),
+        "job" => Ok(Submission::Story(Story::Job(Job {
+            id: item.id,
+            deleted: item.author.is_none(),
+            dead: false,
+            by: item.author.unwrap_or_default(),
+            title: item.title.unwrap_or_default(),
+            time: item.created_at_i,
+            url: item.url,
+            text: item.text,
+        }))),
+        "poll" => Ok(Submission::Story(Story::Poll(Poll {
+            id: item.id,
+            deleted: item.author.is_none(),
+            dead: false,
+            by: item.author.unwrap_or_default(),
+            parts: item.options.iter().map(|option| option.id).collect(),
+            title: item.title.unwrap_or_default(),
+            time: item.created_at_i,
+            text: item.text,
+            score: item.points.unwrap_or(0),
+            descendants,
+        }))),
+        "comment" => Ok(Submission::Comment(Comment {
+            id: item.id,
+            deleted: item.author.is_none() && item.text.is_none(),
+            by: item.author,
+            children,
+            parent: item.parent_id.unwrap_or(0),
+            text: item.text,
+            time: item.created_at_i,
+            dead: false,
+        })),
+        _ => Err(HnError::UnexpectedItemType {
+            id: item.id,
+            expected: "story or comment",
+        }),
+    }
+}
+
+// Coerse item -> public facing PollOption struct
+fn item_to_poll_option(item: AlgoliaItem) -> Result<PollOption, HnError> {
+    match item.item_type.as_str() {
+        "pollopt" => Ok(PollOption {
+            id: item.id,
+            poll: item.parent_id.unwrap_or(0),
+            text: item.text.unwrap_or_default(),
+            score: item.points.unwrap_or(0),
+        }),
+        _ => Err(HnError::UnexpectedItemType {
+            id: item.id,
+            expected: "pollopt",
+        }),
+    }
+}
+
+fn item_to_any_item(item: AlgoliaItem) -> Result<AnyItem, HnError> {
+    match item.item_type.as_str() {
+        "pollopt" => item_to_poll_option(item).map(AnyItem::PollOption),
+        _ => item_to_submission(item).map(AnyItem::from),
+    }
+}
+
+fn to_batch<T>(
+    item_responses: Vec<(u32, Result<AlgoliaItem, HnError>)>,
+    convert: fn(AlgoliaItem) -> Result<T, HnError>,
+) -> BatchResult<T> {
+    let mut result = BatchResult::new();
+    for (id, item_response) in item_responses {
+        match item_response.and_then(convert) {
+            Ok(item) => result.items.push(item),
+            Err(error) => result.failed.push((id, error)),
+        }
+    }
+    result
+}
+
+// The closest search to each of the HN API's story lists
+fn story_list_query(story_type: &StoryListType) -> (&'static str, Tag) {
+    match story_type {
+        StoryListType::Top => ("search", Tag::FrontPage),
+        StoryListType::Best => ("search", Tag::Story),
+        StoryListType::New => ("search_by_date", Tag::Story),
+        StoryListType::Ask => ("search_by_date", Tag::AskHn),
+        StoryListType::Show => ("search_by_date", Tag::ShowHn),
+        StoryListType::Job => ("search_by_date", Tag::Job),
+    }
+}
+
+pub struct AlgoliaClientConfig {
+    pub base_url: String,
+    pub timeout: Duration,
+    // Load whole comment trees with a single request, see `get_full_comment_tree`
+    pub comment_trees: bool,
+}
+
+impl Default for AlgoliaClientConfig {
+    fn default() -> AlgoliaClientConfig {
+        AlgoliaClientConfig {
+            base_url: String::from(DEFAULT_SEARCH_URL),
+            timeout: Duration::from_secs(REQUEST_TIMEOUT_SECS),
+            comment_trees: false,
+        }
+    }
+}
+
+impl AlgoliaClientConfig {
+    // The timeout is shared with the HN API client
+    pub fn from_env() -> AlgoliaClientConfig {
+        let mut config = AlgoliaClientConfig::default();
+        if let Ok(base_url) = std::env::var("HN_SEARCH_URL") {
+            config.base_url = base_url.trim_end_matches('/').to_string();
+        }
+        if let Some(timeout) = std::env::var("HN_API_TIMEOUT_SECS")
+            .ok()
+            .and_then(|timeout| timeout.parse().ok())
+        {
+            config.timeout = Duration::from_secs(timeout);
+        }
+        if let Ok(comment_trees) = std::env::var("HN_SEARCH_COMMENT_TREES") {
+            config.comment_trees = comment_trees == "true";
+        }
+        config
+    }
+}
+
+pub struct AlgoliaClient {
+    base_url: String,
+    timeout: Duration,
+    client: reqwest::Client,
+}
+
+impl AlgoliaClient {
+    // `client` is usually shared with the HN API client, see `HnClient::http_client`
+    pub fn init(config: AlgoliaClientConfig, client: reqwest::Client) -> AlgoliaClient {
+        AlgoliaClient {
+            base_url: config.base_url,
+            timeout: config.timeout,
+            client,
+        }
+    }
+
+    // Hits sorted by relevance, then points and number of comments
+    pub async fn search(&self, query: &SearchQuery) -> Result<SearchResults, HnError> {
+        self.get_search_results("search", query).await
+    }
+
+    // Hits sorted by date, most recent first
+    pub async fn search_by_date(&self, query: &SearchQuery) -> Result<SearchResults, HnError> {
+        self.get_search_results("search_by_date", query).await
+    }
+
+    // All the comments under `parent` (a story or comment), from a single request
+    // instead of one request per comment. Threads nested deeper than serde_json's
+    // recursion limit fail to decode, callers should fall back to the HN API
+    pub async fn get_full_comment_tree(&self, parent: u32) -> Result<CommentTree, HnError> {
+        let item = self.get_algolia_item(parent).await?;
+        let mut comments = Vec::new();
+        collect_comments(item.children, item.id, &mut comments);
+        Ok(CommentTree {
+            comments,
+            more_replies: Vec::new(),
+            failed: Vec::new(),
+        })
+    }
+
+    async fn get_and_jsonify<T: DeserializeOwned>(
+        &self,
+        url: &str,
+        params: &[(&str, String)],
+    ) -> Result<T, HnError> {
+        let response = self
+            .client
+            .get(url)
+            .query(params)
+            .timeout(self.timeout)
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(HnError::Status(response.status()));
+        }
+        let body = response.bytes().await?;
+        Ok(serde_json::from_slice(&body)?)
+    }
+
+    // Items that do not exist are a 404
+    async fn get_algolia_item(&self, id: u32) -> Result<AlgoliaItem, HnError> {
+        let url = format!("{}/items/{}", self.base_url, id);
+        match self.get_and_jsonify(&url, &[]).await {
+            Err(HnError::Status(reqwest::StatusCode::NOT_FOUND)) => Err(HnError::NullItem(id)),
+            item_response => item_response,
+        }
+    }
+
+    // Items are returned in the same order as `ids`
+    async fn get_items(&self, ids: &[u32]) -> Vec<(u32, Result<AlgoliaItem, HnError>)> {
+        stream::iter(ids.iter().cloned())
+            .map(|id| async move { (id, self.get_algolia_item(id).await) })
+            .buffered(PARALLEL_REQUESTS)
+            .collect()
+            .await
+    }
+
+    async fn search_hits(
+        &self,
+        endpoint: &str,
+        query: &SearchQuery,
+    ) -> Result<AlgoliaResponse, HnError> {
+        let url = format!("{}/{}", self.base_url, endpoint);
+        self.get_and_jsonify(&url, &query.to_params()).await
+    }
+
+    async fn get_search_results(
+        &self,
+        endpoint: &str,
+        query: &SearchQuery,
+    ) -> Result<SearchResults, HnError> {
+        let response = self.search_hits(endpoint, query).await?;
+
+        let mut hits = BatchResult::new();
+        for hit in response.hits {
+            let id = hit.id;
+            match to_submission(hit) {
+                Ok(submission) => hits.items.push(submission),
+                Err(error) => hits.failed.push((id, error)),
+            }
+        }
+        Ok(SearchResults {
+            hits,
+            page: response.page,
+            pages: response.nb_pages,
+            total: response.nb_hits,
+        })
+    }
+}
+
+impl HnBackend for AlgoliaClient {
+    fn get_post_ids<'a>(
+        &'a self,
+        story_type: &'a StoryListType,
+    ) -> BoxFuture<'a, Result<Vec<u32>, HnError>> {
+        async move {
+            let (endpoint, tag) = story_list_query(story_type);
+            let query = SearchQuery {
+                tags: vec![tag],
+                hits_per_page: LIST_SIZE,
+                ..SearchQuery::default()
+            };
+            let response = self.search_hits(endpoint, &query).await?;
+            Ok(response.hits.iter().map(|hit| hit.id).collect())
+        }
+        .boxed()
+    }
+
+    fn get_submissions<'a>(&'a self, ids: &'a [u32]) -> BoxFuture<'a, BatchResult<Submission>> {
+        async move { to_batch(self.get_items(ids).await, item_to_submission) }.boxed()
+    }
+
+    fn get_poll_options<'a>(&'a self, ids: &'a [u32]) -> BoxFuture<'a, BatchResult<PollOption>> {
+        async move { to_batch(self.get_items(ids).await, item_to_poll_option) }.boxed()
+    }
+
+    // Items come with their whole reply tree, only the ids of direct replies are kept
+    fn get_item(&self, id: u32) -> BoxFuture<'_, Result<AnyItem, HnError>> {
+        async move { self.get_algolia_item(id).await.and_then(item_to_any_item) }.boxed()
+    }
+
+    // Profiles do not list submissions, the most recent ones are searched for instead
+    fn get_user<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Result<User, HnError>> {
+        async move {
+            let url = format!("{}/users/{}", self.base_url, id);
+            let user: AlgoliaUser = match self.get_and_jsonify(&url, &[]).await {
+                Err(HnError::Status(reqwest::StatusCode::NOT_FOUND)) => {
+                    return Err(HnError::NullUser(id.to_string()))
+                }
+                user => user?,
+            };
+            let query = SearchQuery {
+                tags: vec![Tag::Author(user.username.clone())],
+                hits_per_page: LIST_SIZE,
+                ..SearchQuery::default()
+            };
+            let submissions = self.search_hits("search_by_date", &query).await?;
+            Ok(User {
+                id: user.username,
+                created: user.created_at_i,
+                karma: user.karma,
+                about: user.about,
+                submitted: submissions.hits.iter().map(|hit| hit.id).collect(),
+            })
+        }
+        .boxed()
+    }
+
+    // The whole tree is loaded regardless of `limits`. If it can not be loaded,
+    // each of `children` is loaded with its replies instead
+    fn get_comment_tree<'a>(
+        &'a self,
+        parent: u32,
+        children: &'a [u32],
+        _limits: &'a CommentTreeLimits,
+    ) -> BoxFuture<'a, CommentTree> {
+        async move {
+            if let Ok(tree) = self.get_full_comment_tree(parent).await {
+                return tree;
+            }
+            let mut comments = Vec::new();
+            let mut failed = Vec::new();
+            for (id, item_response) in self.get_items(children).await {
+                match item_response {
+                    Ok(item) if item.is_comment() => {
+                        collect_comments(vec![item], parent, &mut comments)
+                    }
+                    Ok(_) => failed.push((
+                        id,
+                        HnError::UnexpectedItemType {
+                            id,
+                            expected: "comment",
+                        },
+                    )),
+                    Err(error) => failed.push((id, error)),
+                }
+            }
+            CommentTree {
+                comments,
+                more_replies: Vec::new(),
+                failed,
+            }
+        }
+        .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::{mock, Matcher};
+
+    fn make_client() -> AlgoliaClient {
+        AlgoliaClient::init(
+            AlgoliaClientConfig {
+                base_url: mockito::server_url(),
+                ..AlgoliaClientConfig::default()
+            },
+            reqwest::Client::new(),
+        )
+    }
+
+    const SEARCH_RESPONSE: &str = r#"{
+        "hits": [
+            {"objectID":"1","_tags":["story","author_pg","story_1"],"author":"pg","created_at_i":100,"title":"Y Combinator","url":"http://ycombinator.com","points":57,"num_comments":15,"story_text":null,"comment_text":null,"parent_id":null},
+            {"objectID":"15","_tags":["comment","author_sama","story_1"],"author":"sama","created_at_i":200,"title":null,"url":null,"points":null,"num_comments":null,"comment_text":"<p>A reply","story_id":1,"parent_id":1},
+            {"objectID":"126809","_tags":["poll","author_pg","story_126809"],"author":"pg","created_at_i":300,"title":"Poll","url":null,"points":46,"num_comments":100}
+        ],
+        "nbHits": 103,
+        "page": 1,
+        "nbPages": 3,
+        "hitsPerPage": 3
+    }"#;
+
+    #[test]
+    fn tags_and_numeric_filters_are_formatted() {
+        let tags: Vec<String> = [Tag::Comment, Tag::Author(String::from("pg"))]
+            .iter()
+            .map(|tag| tag.to_string())
+            .collect();
+        assert_eq!(tags, vec!["comment", "author_pg"]);
+
+        let filters: Vec<String> = vec![
+            (NumericField::NumComments, Comparison::Le),
+            (NumericField::Points, Comparison::Eq),
+            (NumericField::CreatedAt, Comparison::Gt),
+        ]
+        .into_iter()
+        .map(|(field, comparison)| {
+            NumericFilter {
+                field,
+                comparison,
+                value: 10,
+            }
+            .to_string()
+        })
+        .collect();
+        assert_eq!(
+            filters,
+            vec!["num_comments<=10", "points=10", "created_at_i>10"]
+        );
+    }
+
+    #[tokio::test]
+    async fn search_maps_hits_to_stories_and_comments() {
+        let mock_inst = mock("GET", "/search")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("query".into(), "y combinator".into()),
+                Matcher::UrlEncoded("page".into(), "1".into()),
+                Matcher::UrlEncoded("hitsPerPage".into(), "3".into()),
+                Matcher::UrlEncoded("tags".into(), "story,author_pg".into()),
+                Matcher::UrlEncoded(
+                    "numericFilters".into(),
+                    "points>=50,created_at_i<1000".into(),
+                ),
+            ]))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(SEARCH_RESPONSE)
+            .expect(1)
+            .create();
+
+        let query = SearchQuery {
+            query: String::from("y combinator"),
+            tags: vec![Tag::Story, Tag::Author(String::from("pg"))],
+            numeric_filters: vec![
+                NumericFilter {
+                    field: NumericField::Points,
+                    comparison: Comparison::Ge,
+                    value: 50,
+                },
+                NumericFilter {
+                    field: NumericField::CreatedAt,
+                    comparison: Comparison::Lt,
+                    value: 1000,
+                },
+            ],
+            page: 1,
+            hits_per_page: 3,
+        };
+        let results = make_client().search(&query).await.unwrap();
+        mock_inst.assert();
+
+        assert_eq!(results.page, 1);
+        assert_eq!(results.pages, 3);
+        assert_eq!(results.total, 103);
+        assert_eq!(results.hits.items.len(), 2);
+        match &results.hits.items[0] {
+            Submission::Story(Story::Post(post)) => {
+                assert_eq!(post.id, 1);
+                assert_eq!(post.title, "Y Combinator");
+                assert_eq!(post.score, 57);
+                assert_eq!(post.descendants, 15);
+            }
+            _ => panic!("Unexpected Submission variant"),
+        }
+        match &results.hits.items[1] {
+            Submission::Comment(comment) => {
+                assert_eq!(comment.id, 15);
+                assert_eq!(comment.by.as_deref(), Some("sama"));
+                assert_eq!(comment.text.as_deref(), Some("<p>A reply"));
+            }
+            _ => panic!("Unexpected Submission variant"),
+        }
+        // Polls can not be mapped without their options
+        assert_eq!(results.hits.failed.len(), 1);
+        assert_eq!(results.hits.failed[0].0, 126809);
+    }
+
+    #[tokio::test]
+    async fn search_by_date_reports_status_errors() {
+        let mock_inst = mock("GET", "/search_by_date")
+            .match_query(Matcher::UrlEncoded("query".into(), "rust".into()))
+            .with_status(500)
+            .expect(1)
+            .create();
+
+        let query = SearchQuery {
+            query: String::from("rust"),
+            ..SearchQuery::default()
+        };
+        let error = make_client().search_by_date(&query).await.err().unwrap();
+        mock_inst.assert();
+        assert!(matches!(error, HnError::Status(status) if status.as_u16() == 500));
+    }
+
+    #[tokio::test]
+    async fn comment_tree_is_loaded_in_one_request() {
+        let mock_inst = mock("GET", "/items/1")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{"id":1,"type":"story","author":"pg","title":"Y Combinator","text":null,"created_at_i":100,"parent_id":null,"children":[
+                    {"id":15,"type":"comment","author":"sama","text":"first","created_at_i":200,"parent_id":1,"children":[
+                        {"id":17,"type":"comment","author":"pg","text":"reply","created_at_i":300,"parent_id":15,"children":[]}
+                    ]},
+                    {"id":16,"type":"comment","author":null,"text":null,"created_at_i":250,"parent_id":1,"children":[]}
+                ]}"#,
+            )
+            .expect(1)
+            .create();
+
+        let tree = make_client().get_full_comment_tree(1).await.unwrap();
+        mock_inst.assert();
+
+        let links: Vec<(u32, u32, Vec<u32>)> = tree
+            .comments
+            .iter()
+            .map(|comment| (comment.id, comment.parent, comment.children.clone()))
+            .collect();
+        assert_eq!(
+            links,
+            vec![(15, 1, vec![17]), (17, 15, vec![]), (16, 1, vec![])]
+        );
+        assert!(tree.comments[2].deleted);
+        assert!(tree.more_replies.is_empty());
+    }
+
+    #[tokio::test]
+    async fn backend_loads_lists_and_items() {
+        let list_mock = mock("GET", "/search")
+            .match_query(Matcher::UrlEncoded("tags".into(), "front_page".into()))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(SEARCH_RESPONSE)
+            .expect(1)
+            .create();
+        let item_mock = mock("GET", "/items/126809")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{"id":126809,"type":"poll","author":"pg","title":"Poll","created_at_i":300,"points":46,"children":[
+                    {"id":126810,"type":"comment","author":"sama","text":"vote","created_at_i":400,"parent_id":126809,"children":[]}
+                ],"options":[
+                    {"id":126811,"type":"pollopt","author":"pg","text":"Yes","points":10,"created_at_i":300,"parent_id":126809,"children":[]}
+                ]}"#,
+            )
+            .expect(1)
+            .create();
+        let missing_mock = mock("GET", "/items/404").with_status(404).create();
+
+        let client = make_client();
+        let post_ids = HnBackend::get_post_ids(&client, &StoryListType::Top)
+            .await
+            .unwrap();
+        list_mock.assert();
+        assert_eq!(post_ids, vec![1, 15, 126809]);
+
+        let submissions = HnBackend::get_submissions(&client, &[126809, 404]).await;
+        item_mock.assert();
+        missing_mock.assert();
+        match &submissions.items[..] {
+            [Submission::Story(Story::Poll(poll))] => {
+                assert_eq!(poll.parts, vec![126811]);
+                assert_eq!(poll.descendants, 1);
+            }
+            _ => panic!("Unexpected submissions"),
+        }
+        assert!(matches!(
+            submissions.failed[..],
+            [(404, HnError::NullItem(404))]
+        ));
+
+        let option_mock = mock("GET", "/items/126811")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{"id":126811,"type":"pollopt","author":"pg","text":"Yes","points":10,"created_at_i":300,"parent_id":126809,"children":[]}"#,
+            )
+            .expect(1)
+            .create();
+        let option = HnBackend::get_item(&client, 126811).await;
+        option_mock.assert();
+        assert!(matches!(
+            option,
+            Ok(AnyItem::PollOption(PollOption { poll: 126809, .. }))
+        ));
+    }
+}
@@ -0,0 +1,147 @@
+use crate::constants::MAX_ANCESTORS;
+use crate::hn_client::{BatchResult, CommentTree, CommentTreeLimits, HnError, Updates};
+use crate::stores::data::{AnyItem, PollOption, Story, Submission, User};
+use crate::stores::view::StoryListType;
+use futures::future::{self, BoxFuture, FutureExt};
+use futures::stream::{self, BoxStream, StreamExt};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+// A source of HN data - the app only loads data through this trait so that it can
+// run against the HN API, the HN Search API or fixtures
+pub trait HnBackend: Send + Sync {
+    fn get_post_ids<'a>(
+        &'a self,
+        story_type: &'a StoryListType,
+    ) -> BoxFuture<'a, Result<Vec<u32>, HnError>>;
+
+    // Items are returned in the same order as `ids`
+    fn get_submissions<'a>(&'a self, ids: &'a [u32]) -> BoxFuture<'a, BatchResult<Submission>>;
+
+    fn get_poll_options<'a>(&'a self, ids: &'a [u32]) -> BoxFuture<'a, BatchResult<PollOption>>;
+
+    fn get_item(&self, id: u32) -> BoxFuture<'_, Result<AnyItem, HnError>>;
+
+    fn get_user<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Result<User, HnError>>;
+
+    // The comments under `parent`, starting from its `children`
+    fn get_comment_tree<'a>(
+        &'a self,
+        parent: u32,
+        children: &'a [u32],
+        limits: &'a CommentTreeLimits,
+    ) -> BoxFuture<'a, CommentTree>;
+
+    // Yields each story along with its index in `post_ids`
+    // By default all stories are yielded once the whole batch has loaded
+    fn stream_stories<'a>(
+        &'a self,
+        post_ids: &'a [u32],
+    ) -> BoxStream<'a, (usize, Result<Story, HnError>)> {
+        self.get_submissions(post_ids)
+            .map(move |submissions| {
+                let mut stories: HashMap<u32, Result<Story, HnError>> = HashMap::new();
+                for submission in submissions.items {
+                    match submission {
+                        Submission::Story(story) => {
+                            stories.insert(story_id(&story), Ok(story));
+                        }
+                        Submission::Comment(comment) => {
+                            let error = HnError::UnexpectedItemType {
+                                id: comment.id,
+                                expected: "story",
+                            };
+                            stories.insert(comment.id, Err(error));
+                        }
+                    }
+                }
+                stories.extend(
+                    submissions
+                        .failed
+                        .into_iter()
+                        .map(|(id, error)| (id, Err(error))),
+                );
+                stream::iter(
+                    post_ids.iter().enumerate().filter_map(move |(index, id)| {
+                        stories.remove(id).map(|story| (index, story))
+                    }),
+                )
+            })
+            .flatten_stream()
+            .boxed()
+    }
+
+    // `id` and the items above it, nearest first, following `parent` links up to the
+    // story at the root - stops at the first item that fails to load
+    fn get_ancestors(&self, id: u32) -> BoxFuture<'_, BatchResult<AnyItem>> {
+        async move {
+            let mut ancestors = BatchResult::new();
+            let mut next = Some(id);
+            while let Some(id) = next.take() {
+                match self.get_item(id).await {
+                    Ok(AnyItem::Comment(comment)) => {
+                        // The limit guards against parent links that loop
+                        if ancestors.items.len() < MAX_ANCESTORS {
+                            next = Some(comment.parent);
+                        }
+                        ancestors.items.push(AnyItem::Comment(comment));
+                    }
+                    Ok(item) => ancestors.items.push(item),
+                    Err(error) => ancestors.failed.push((id, error)),
+                }
+            }
+            ancestors
+        }
+        .boxed()
+    }
+
+    // Items and profiles that changed recently
+    // Backends that can not tell what changed report nothing
+    fn get_updates(&self) -> BoxFuture<'_, Result<Updates, HnError>> {
+        future::ready(Ok(Updates {
+            items: Vec::new(),
+            profiles: Vec::new(),
+        }))
+        .boxed()
+    }
+
+    // The id of the most recently created item, if known
+    fn get_max_item(&self) -> BoxFuture<'_, Result<Option<u32>, HnError>> {
+        future::ready(Ok(None)).boxed()
+    }
+
+    // The number of requests currently allowed in flight, for backends that adjust it
+    fn concurrency_limit(&self) -> Option<usize> {
+        None
+    }
+}
+
+fn story_id(story: &Story) -> u32 {
+    match story {
+        Story::Post(post) => post.id,
+        Story::Job(job) => job.id,
+        Story::Poll(poll) => poll.id,
+    }
+}
+
+// The backend to load data from, set with HN_BACKEND
+pub enum BackendKind {
+    // The HN API, see `HnClient`
+    Firebase,
+    // The HN Search API, see `AlgoliaClient`
+    Algolia,
+    // JSON files laid out like the HN API, see `FixtureBackend`
+    Fixture(PathBuf),
+}
+
+impl BackendKind {
+    pub fn from_env() -> BackendKind {
+        match std::env::var("HN_BACKEND").as_deref() {
+            Ok("algolia") => BackendKind::Algolia,
+            Ok("fixture") => BackendKind::Fixture(PathBuf::from(
+                std::env::var("HN_FIXTURE_DIR").unwrap_or_else(|_| String::from("fixtures")),
+            )),
+            _ => BackendKind::Firebase,
+        }
+    }
+}
@@ -0,0 +1,200 @@
+use crate::ui;
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+use crossterm::Result as CrossTermResult;
+
+const DEFAULT_LAUNCHER: &str = "xdg-open";
+
+// Opens links outside the app, e.g. a story's url in a web browser
+pub trait Launcher {
+    // Returns once the launcher has started, or has exited if it blocks
+    fn open(&self, url: &str) -> io::Result<()>;
+    // Whether the launcher takes over the terminal until it exits, e.g. a text browser
+    fn blocks(&self) -> bool;
+}
+
+pub struct LauncherConfig {
+    // The command links are opened with, `%s` is replaced with the url
+    // or the url is added as the last argument
+    pub command: String,
+    pub blocking: bool,
+}
+
+impl Default for LauncherConfig {
+    fn default() -> LauncherConfig {
+        LauncherConfig {
+            command: String::from(DEFAULT_LAUNCHER),
+            blocking: false,
+        }
+    }
+}
+
+impl LauncherConfig {
+    // HN_BROWSER takes precedence over BROWSER, which can list several commands
+    // separated by `:` of which the first is used
+    pub fn from_env() -> LauncherConfig {
+        let mut config = LauncherConfig::default();
+        if let Some(command) = std::env::var("HN_BROWSER")
+            .ok()
+            .or_else(|| {
+                std::env::var("BROWSER")
+                    .ok()
+                    .and_then(|browser| browser.split(':').next().map(String::from))
+            })
+            .filter(|command| !command.trim().is_empty())
+        {
+            config.command = command;
+        }
+        if let Ok(blocking) = std::env::var("HN_BROWSER_BLOCKING") {
+            config.blocking = blocking == "true";
+        }
+        config
+    }
+}
+
+// Runs a command to open links, see `LauncherConfig`
+pub struct CommandLauncher {
+    program: String,
+    args: Vec<String>,
+    blocking: bool,
+}
+
+impl CommandLauncher {
+    pub fn init(config: LauncherConfig) -> CommandLauncher {
+        let mut words = config.command.split_whitespace().map(String::from);
+        CommandLauncher {
+            program: words
+                .next()
+                .unwrap_or_else(|| String::from(DEFAULT_LAUNCHER)),
+            args: words.collect(),
+            blocking: config.blocking,
+        }
+    }
+
+    fn command(&self, url: &str) -> Command {
+        let mut command = Command::new(&self.program);
+        if self.args.iter().any(|arg| arg.contains("%s")) {
+            command.args(self.args.iter().map(|arg| arg.replace("%s", url)));
+        } else {
+            command.args(&self.args).arg(url);
+        }
+        command
+    }
+}
+
+impl Launcher for CommandLauncher {
+    fn open(&self, url: &str) -> io::Result<()> {
+        let mut command = self.command(url);
+        if self.blocking {
+            let status = command.status()?;
+            if !status.success() {
+                let message = format!("{} exited with {}", self.program, status);
+                return Err(io::Error::other(message));
+            }
+            return Ok(());
+        }
+        // Output is discarded so it doesn't draw over the app
+        let mut child = command
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()?;
+        // Reaps the launcher when it exits, which is often right away for xdg-open
+        std::thread::spawn(move || child.wait());
+        Ok(())
+    }
+
+    fn blocks(&self) -> bool {
+        self.blocking
+    }
+}
+
+// The HN page for an item, with its comments
+pub fn discussion_url(item_id: u32) -> String {
+    format!("https://news.ycombinator.com/item?id={}", item_id)
+}
+
+// Opens `url` with `launcher`, handing the terminal over to it if it blocks
+// Terminal errors are returned in the outer result and launcher errors in the inner one
+pub fn open_url<W>(w: &mut W, launcher: &dyn Launcher, url: &str) -> CrossTermResult<io::Result<()>>
+where
+    W: Write,
+{
+    if !launcher.blocks() {
+        return Ok(launcher.open(url));
+    }
+    ui::teardown_screen(w)?;
+    let result = launcher.open(url);
+    ui::initialize_screen(w)?;
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    // Records the urls it is asked to open
+    struct StubLauncher {
+        opened: RefCell<Vec<String>>,
+    }
+
+    impl Launcher for StubLauncher {
+        fn open(&self, url: &str) -> io::Result<()> {
+            self.opened.borrow_mut().push(url.to_string());
+            Ok(())
+        }
+
+        fn blocks(&self) -> bool {
+            false
+        }
+    }
+
+    fn launcher(command: &str, blocking: bool) -> CommandLauncher {
+        CommandLauncher::init(LauncherConfig {
+            command: command.to_string(),
+            blocking,
+        })
+    }
+
+    #[test]
+    fn urls_are_added_to_the_command() {
+        let url = "https://example.com/a?b=1";
+        let command = launcher("firefox --new-tab", false).command(url);
+        assert_eq!(command.get_program(), "firefox");
+        let args: Vec<_> = command.get_args().collect();
+        assert_eq!(args, vec!["--new-tab", url]);
+
+        let command = launcher("w3m -o a=1 %s", true).command(url);
+        let args: Vec<_> = command.get_args().collect();
+        assert_eq!(args, vec!["-o", "a=1", url]);
+    }
+
+    #[test]
+    fn launcher_failures_are_returned() {
+        let url = "https://example.com";
+        assert!(launcher("true", true).open(url).is_ok());
+        assert!(launcher("false", true).open(url).is_err());
+        assert!(launcher("true", false).open(url).is_ok());
+        assert!(launcher("rust-hn-client-missing-launcher", false)
+            .open(url)
+            .is_err());
+    }
+
+    #[test]
+    fn urls_are_opened_with_the_launcher() {
+        let stub = StubLauncher {
+            opened: RefCell::new(Vec::new()),
+        };
+        let mut output = Vec::new();
+        let url = discussion_url(8863);
+        assert!(open_url(&mut output, &stub, &url).unwrap().is_ok());
+        assert_eq!(
+            *stub.opened.borrow(),
+            vec!["https://news.ycombinator.com/item?id=8863"]
+        );
+        // Nothing is drawn for launchers that don't take over the terminal
+        assert!(output.is_empty());
+    }
+}
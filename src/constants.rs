@@ -1,4 +1,28 @@
 pub const PARALLEL_REQUESTS: usize = 5;
+pub const MIN_PARALLEL_REQUESTS: usize = 1;
+pub const MAX_PARALLEL_REQUESTS: usize = 32;
 pub const PAGE_SIZE: u8 = 20;
 pub const POST_ROW_SIZE: u8 = 3;
 pub const LEFT_OFFSET: u16 = 3;
+pub const DETAILS_HEADER_SIZE: u8 = 6;
+pub const USER_HEADER_SIZE: u8 = 8;
+pub const CONTEXT_SIZE: u8 = 5;
+pub const COMMENT_ROW_SIZE: u8 = 4;
+pub const COMMENT_INDENT: u16 = 2;
+pub const COMMENT_TREE_MAX_DEPTH: usize = 5;
+pub const COMMENT_TREE_MAX_CHILDREN: usize = 10;
+pub const COMMENT_TREE_MAX_ITEMS: usize = 100;
+pub const MAX_ANCESTORS: usize = 1000;
+pub const MAX_LINKS: usize = 9;
+pub const REQUEST_TIMEOUT_SECS: u64 = 10;
+pub const POOL_IDLE_TIMEOUT_SECS: u64 = 90;
+pub const TCP_KEEPALIVE_SECS: u64 = 60;
+pub const USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+pub const BATCH_TIMEOUT_SECS: u64 = 30;
+pub const UPDATE_INTERVAL_SECS: u64 = 30;
+pub const STREAM_RECONNECT_DELAY_MILLIS: u64 = 1000;
+pub const TICK_MILLIS: u64 = 200;
+pub const MAX_RETRIES: u32 = 3;
+pub const RETRY_BASE_DELAY_MILLIS: u64 = 250;
+pub const RETRY_MAX_DELAY_MILLIS: u64 = 4000;
+pub const REQUESTS_PER_SECOND: u32 = 50;
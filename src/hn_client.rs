@@ -1,29 +1,51 @@
-use crate::constants::PARALLEL_REQUESTS;
-use crate::stores::data::{Comment, Post};
-use crate::stores::view::StoryListType;
-use futures::{future, stream, StreamExt};
-use reqwest::get;
-use serde::{Deserialize, Serialize};
+mod concurrency;
+mod event_stream;
+mod fixture;
+mod rate_limit;
+mod retry;
+mod single_flight;
+mod sse;
 
-#[cfg(test)]
-use mockito;
+pub use fixture::FixtureBackend;
 
-#[cfg(test)]
-fn get_hn_url() -> String {
-    let url = mockito::server_url();
-    url
-}
+use crate::backend::HnBackend;
+use crate::constants::{
+    BATCH_TIMEOUT_SECS, MAX_PARALLEL_REQUESTS, MAX_RETRIES, MIN_PARALLEL_REQUESTS,
+    PARALLEL_REQUESTS, POOL_IDLE_TIMEOUT_SECS, REQUESTS_PER_SECOND, REQUEST_TIMEOUT_SECS,
+    RETRY_BASE_DELAY_MILLIS, RETRY_MAX_DELAY_MILLIS, STREAM_RECONNECT_DELAY_MILLIS,
+    TCP_KEEPALIVE_SECS, USER_AGENT,
+};
+use crate::stores::data::{
+    AnyItem, Comment, Job, MoreReplies, Poll, PollOption, Post, Story, Submission, User,
+};
+use crate::stores::view::StoryListType;
+use bytes::Bytes;
+use concurrency::{AdaptiveLimit, Outcome};
+use futures::future::{abortable, AbortHandle, BoxFuture, FutureExt};
+use futures::stream::BoxStream;
+use futures::{stream, Future, Stream, StreamExt};
+use rate_limit::RateLimiter;
+use retry::RetryPolicy;
+use serde::{Deserialize, Serialize};
+use single_flight::SingleFlight;
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::time::{delay_for, timeout, timeout_at};
 
-#[cfg(not(test))]
-fn get_hn_url() -> String {
-    String::from("https://hacker-news.firebaseio.com/v0")
-}
+const DEFAULT_HN_URL: &str = "https://hacker-news.firebaseio.com/v0";
 
 enum Route {
     New,
     Top,
     Best,
+    Ask,
+    Show,
+    Job,
     Item,
+    User,
+    Updates,
+    MaxItem,
 }
 
 // Defined by https://github.com/HackerNews/API#items
@@ -33,152 +55,949 @@ enum Item {
     Job(HnJob),
     Story(HnStory),
     Comment(HnComment),
-    Poll,
-    Pollopt,
+    Poll(HnPoll),
+    Pollopt(HnPollopt),
 }
 
 // A story can be an Ask or Story
 // Ask -> Some(String)
 // Story -> None
+// `kids` is omitted by the API when there are no comments, and deleted stories only
+// have `id`, `time` and `deleted`
 #[derive(Debug, Deserialize, Serialize, Clone)]
 struct HnStory {
+    #[serde(default)]
     by: String,
+    #[serde(default)]
+    dead: bool,
+    #[serde(default)]
+    deleted: bool,
+    #[serde(default)]
     descendants: u32,
     id: u32,
+    #[serde(default)]
     kids: Vec<u32>,
     text: Option<String>,
+    #[serde(default)]
     score: u16,
     time: u32,
+    #[serde(default)]
     title: String,
     url: Option<String>,
 }
 
+// Jobs either link to an application url or have the description in text
+// Like stories, deleted jobs only have `id`, `time` and `deleted`
 #[derive(Debug, Deserialize, Serialize)]
 struct HnJob {
+    #[serde(default)]
     by: String,
+    #[serde(default)]
+    dead: bool,
+    #[serde(default)]
+    deleted: bool,
     id: u32,
+    #[serde(default)]
     score: u16,
-    text: String,
+    text: Option<String>,
     time: u32,
+    #[serde(default)]
     title: String,
-    url: String,
+    url: Option<String>,
 }
 
+// The options of a poll are listed in `parts`
+// Like stories, deleted polls only have `id`, `time` and `deleted`
 #[derive(Debug, Deserialize, Serialize)]
-struct HnComment {
+struct HnPoll {
+    #[serde(default)]
     by: String,
+    #[serde(default)]
+    dead: bool,
+    #[serde(default)]
+    deleted: bool,
+    #[serde(default)]
+    descendants: u32,
     id: u32,
+    #[serde(default)]
     kids: Vec<u32>,
-    parent: u32,
+    #[serde(default)]
+    parts: Vec<u32>,
+    #[serde(default)]
+    score: u16,
+    text: Option<String>,
+    time: u32,
+    #[serde(default)]
+    title: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct HnPollopt {
+    by: String,
+    id: u32,
+    poll: u32,
+    score: u16,
     text: String,
     time: u32,
 }
 
-fn get_route(route: Route) -> String {
-    let path = match route {
-        Route::New => "/newstories.json",
-        Route::Top => "/topstories.json",
-        Route::Best => "/beststories.json",
-        Route::Item => "/item",
-    };
-    let base_hn_url = get_hn_url();
-    return format!("{base_url}{path}", base_url = base_hn_url, path = path);
+// Deleted comments have no `by` or `text` but may still have replies
+#[derive(Debug, Deserialize, Serialize)]
+struct HnComment {
+    by: Option<String>,
+    #[serde(default)]
+    dead: bool,
+    #[serde(default)]
+    deleted: bool,
+    id: u32,
+    #[serde(default)]
+    kids: Vec<u32>,
+    parent: u32,
+    text: Option<String>,
+    time: u32,
+}
+
+// Users are identified by their case-sensitive username
+#[derive(Debug, Deserialize, Serialize)]
+struct HnUser {
+    about: Option<String>,
+    created: u32,
+    id: String,
+    karma: i32,
+    #[serde(default)]
+    submitted: Vec<u32>,
+}
+
+// Items and profiles that changed recently
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Updates {
+    pub items: Vec<u32>,
+    pub profiles: Vec<String>,
+}
+
+#[derive(Debug)]
+pub enum HnError {
+    Network(reqwest::Error),
+    Status(reqwest::StatusCode),
+    Decode(serde_json::Error),
+    // The API returns `null` for items and users that do not exist
+    NullItem(u32),
+    NullUser(String),
+    UnexpectedItemType { id: u32, expected: &'static str },
+    // The server ended an event stream, e.g. with a `cancel` event
+    StreamClosed(String),
+    // The request or the batch it was part of took longer than the timeout
+    Timeout(Duration),
+    // The request was retried and still failed, `error` is from the last attempt
+    Retried { attempts: u32, error: Box<HnError> },
+    // Reading data from files, see `FixtureBackend`
+    Io(std::io::Error),
+    // The error from a request shared by concurrent fetches of the same route
+    Shared(Arc<HnError>),
+}
+
+impl fmt::Display for HnError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HnError::Network(error) => write!(f, "network error: {}", error),
+            HnError::Status(status) => write!(f, "unexpected response status: {}", status),
+            HnError::Decode(error) => write!(f, "invalid response body: {}", error),
+            HnError::NullItem(id) => write!(f, "item {} does not exist", id),
+            HnError::NullUser(id) => write!(f, "user {} does not exist", id),
+            HnError::UnexpectedItemType { id, expected } => {
+                write!(f, "item {} is not a {}", id, expected)
+            }
+            HnError::StreamClosed(event) => write!(f, "stream closed by server: {}", event),
+            HnError::Timeout(duration) => write!(f, "timed out after {:?}", duration),
+            HnError::Retried { attempts, error } => {
+                write!(f, "{} (after {} attempts)", error, attempts)
+            }
+            HnError::Io(error) => write!(f, "io error: {}", error),
+            HnError::Shared(error) => error.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for HnError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            HnError::Network(error) => Some(error),
+            HnError::Decode(error) => Some(error),
+            HnError::Retried { error, .. } => Some(error.as_ref()),
+            HnError::Io(error) => Some(error),
+            HnError::Shared(error) => error.source(),
+            _ => None,
+        }
+    }
 }
 
-fn get_item_route(id: &u32) -> String {
-    let base_url = get_route(Route::Item);
-    return format!("{base_url}/{id}.json", base_url = base_url, id = id);
+impl HnError {
+    // Errors that may not happen again, e.g. dropped connections and server errors
+    fn is_retryable(&self) -> bool {
+        match self {
+            HnError::Network(error) => {
+                error.is_timeout() || error.is_connect() || error.is_request() || error.is_body()
+            }
+            HnError::Status(status) => {
+                status.is_server_error() || *status == reqwest::StatusCode::TOO_MANY_REQUESTS
+            }
+            HnError::Timeout(_) => true,
+            _ => false,
+        }
+    }
 }
 
-async fn get_and_jsonify<T>(route: &str) -> Result<T, Box<dyn std::error::Error>>
+impl From<reqwest::Error> for HnError {
+    fn from(error: reqwest::Error) -> HnError {
+        HnError::Network(error)
+    }
+}
+
+impl From<serde_json::Error> for HnError {
+    fn from(error: serde_json::Error) -> HnError {
+        HnError::Decode(error)
+    }
+}
+
+impl From<std::io::Error> for HnError {
+    fn from(error: std::io::Error) -> HnError {
+        HnError::Io(error)
+    }
+}
+
+// Items fetched in a batch along with the ids that could not be fetched
+pub struct BatchResult<T> {
+    pub items: Vec<T>,
+    pub failed: Vec<(u32, HnError)>,
+}
+
+impl<T> BatchResult<T> {
+    pub fn new() -> BatchResult<T> {
+        BatchResult {
+            items: Vec::new(),
+            failed: Vec::new(),
+        }
+    }
+}
+
+// How loaded data is kept up to date
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Transport {
+    // Periodic requests to /updates.json and /maxitem.json
+    Poll,
+    // Changes are pushed through Firebase event streams
+    Stream,
+}
+
+pub struct HnClientConfig {
+    pub base_url: String,
+    // Number of requests in flight to start with, adjusted between
+    // `min_concurrency` and `max_concurrency` as requests succeed or fail
+    pub concurrency: usize,
+    pub min_concurrency: usize,
+    pub max_concurrency: usize,
+    // Applies to each request on its own, retries get a fresh timeout
+    pub timeout: Duration,
+    // Applies to all the requests of a batch, e.g. one `get_submissions` call
+    pub batch_timeout: Duration,
+    pub user_agent: String,
+    pub transport: Transport,
+    // Delay before an event stream is reopened, doubled on each failed attempt
+    pub stream_reconnect_delay: Duration,
+    // Failed requests are retried after a random delay of up to
+    // `retry_base_delay`, doubled on each retry up to `retry_max_delay`
+    pub max_retries: u32,
+    pub retry_base_delay: Duration,
+    pub retry_max_delay: Duration,
+    // Shared by all requests, 0 disables the limit
+    pub requests_per_second: u32,
+}
+
+impl Default for HnClientConfig {
+    fn default() -> HnClientConfig {
+        HnClientConfig {
+            base_url: String::from(DEFAULT_HN_URL),
+            concurrency: PARALLEL_REQUESTS,
+            min_concurrency: MIN_PARALLEL_REQUESTS,
+            max_concurrency: MAX_PARALLEL_REQUESTS,
+            timeout: Duration::from_secs(REQUEST_TIMEOUT_SECS),
+            batch_timeout: Duration::from_secs(BATCH_TIMEOUT_SECS),
+            user_agent: String::from(USER_AGENT),
+            transport: Transport::Poll,
+            stream_reconnect_delay: Duration::from_millis(STREAM_RECONNECT_DELAY_MILLIS),
+            max_retries: MAX_RETRIES,
+            retry_base_delay: Duration::from_millis(RETRY_BASE_DELAY_MILLIS),
+            retry_max_delay: Duration::from_millis(RETRY_MAX_DELAY_MILLIS),
+            requests_per_second: REQUESTS_PER_SECOND,
+        }
+    }
+}
+
+impl HnClientConfig {
+    // Allows the defaults to be overridden without recompiling, e.g. to point
+    // at a mirror of the API with HN_API_URL
+    pub fn from_env() -> HnClientConfig {
+        let mut config = HnClientConfig::default();
+        if let Ok(base_url) = std::env::var("HN_API_URL") {
+            config.base_url = base_url.trim_end_matches('/').to_string();
+        }
+        if let Some(concurrency) = std::env::var("HN_API_CONCURRENCY")
+            .ok()
+            .and_then(|concurrency| concurrency.parse().ok())
+        {
+            config.concurrency = concurrency;
+        }
+        if let Some(min_concurrency) = std::env::var("HN_API_MIN_CONCURRENCY")
+            .ok()
+            .and_then(|min_concurrency| min_concurrency.parse().ok())
+        {
+            config.min_concurrency = min_concurrency;
+        }
+        if let Some(max_concurrency) = std::env::var("HN_API_MAX_CONCURRENCY")
+            .ok()
+            .and_then(|max_concurrency| max_concurrency.parse().ok())
+        {
+            config.max_concurrency = max_concurrency;
+        }
+        if let Some(timeout) = std::env::var("HN_API_TIMEOUT_SECS")
+            .ok()
+            .and_then(|timeout| timeout.parse().ok())
+        {
+            config.timeout = Duration::from_secs(timeout);
+        }
+        if let Some(batch_timeout) = std::env::var("HN_API_BATCH_TIMEOUT_SECS")
+            .ok()
+            .and_then(|batch_timeout| batch_timeout.parse().ok())
+        {
+            config.batch_timeout = Duration::from_secs(batch_timeout);
+        }
+        if let Ok(user_agent) = std::env::var("HN_API_USER_AGENT") {
+            config.user_agent = user_agent;
+        }
+        if let Some(max_retries) = std::env::var("HN_API_MAX_RETRIES")
+            .ok()
+            .and_then(|max_retries| max_retries.parse().ok())
+        {
+            config.max_retries = max_retries;
+        }
+        if let Some(requests_per_second) = std::env::var("HN_API_REQUESTS_PER_SECOND")
+            .ok()
+            .and_then(|requests_per_second| requests_per_second.parse().ok())
+        {
+            config.requests_per_second = requests_per_second;
+        }
+        match std::env::var("HN_API_TRANSPORT").as_deref() {
+            Ok("stream") => config.transport = Transport::Stream,
+            Ok("poll") => config.transport = Transport::Poll,
+            _ => {}
+        }
+        config
+    }
+}
+
+pub struct HnClient {
+    base_url: String,
+    // Upper bound of `concurrency_limit`, the number of requests batches buffer
+    max_concurrency: usize,
+    concurrency_limit: AdaptiveLimit,
+    timeout: Duration,
+    batch_timeout: Duration,
+    // Shared by every request, see `build_http_client`
+    client: reqwest::Client,
+    stream_reconnect_delay: Duration,
+    transport: Transport,
+    retry_policy: RetryPolicy,
+    rate_limiter: RateLimiter,
+    // Response bodies by route, so concurrent fetches of the same item or list only
+    // send one request
+    in_flight: SingleFlight<Result<Bytes, Arc<HnError>>>,
+}
+
+// A fetch running in the background, see `spawn_fetch`
+pub struct FetchHandle {
+    abort_handle: AbortHandle,
+}
+
+impl FetchHandle {
+    // Stops the fetch - requests in flight are dropped and nothing more is sent
+    pub fn cancel(&self) {
+        self.abort_handle.abort();
+    }
+}
+
+// Runs `fetch` in the background until it completes or is cancelled
+pub fn spawn_fetch<F>(fetch: F) -> FetchHandle
 where
-    for<'de> T: Deserialize<'de>,
+    F: Future<Output = ()> + Send + 'static,
 {
-    let body: T = get(route).await?.json().await?;
-    Ok(body)
+    let (fetch, abort_handle) = abortable(fetch);
+    tokio::spawn(fetch);
+    FetchHandle { abort_handle }
 }
 
-async fn get_items(ids: &[u32]) -> Vec<Item> {
-    stream::iter(ids)
-        .map(|item_id| async move {
-            let route = get_item_route(item_id);
-            get_and_jsonify::<Item>(&route).await
-        })
-        .buffer_unordered(PARALLEL_REQUESTS)
-        // TODO - handle error messaging / logging
-        // Right now errors are silently swallowed
-        .filter(|item_response| future::ready(item_response.is_ok()))
-        .map(|item_response| item_response.unwrap())
-        .collect::<Vec<Item>>()
-        .await
+// Bounds on how much of a comment tree is loaded by `get_comment_tree`
+pub struct CommentTreeLimits {
+    // Number of levels of replies to load - 1 only loads the direct children
+    pub max_depth: usize,
+    // Number of replies to load under any one parent
+    pub max_children: usize,
+    // Total number of comments to load across the tree
+    pub max_items: usize,
 }
 
-pub async fn get_post_ids(
-    story_type: &StoryListType,
-) -> Result<Vec<u32>, Box<dyn std::error::Error>> {
-    let route = match story_type {
-        StoryListType::Top => get_route(Route::Top),
-        StoryListType::Best => get_route(Route::Best),
-        StoryListType::New => get_route(Route::New),
-    };
+pub struct CommentTree {
+    pub comments: Vec<Comment>,
+    // Parents with replies that were not loaded due to the limits
+    pub more_replies: Vec<MoreReplies>,
+    pub failed: Vec<(u32, HnError)>,
+}
 
-    let post_ids = get_and_jsonify::<Vec<u32>>(&route).await?;
-    Ok(post_ids)
+// The id of an item given as a number or an HN item url,
+// e.g. https://news.ycombinator.com/item?id=1
+pub fn parse_item_id(text: &str) -> Option<u32> {
+    let text = text.trim();
+    if let Ok(id) = text.parse() {
+        return Some(id);
+    }
+    let url = text
+        .trim_start_matches("https://")
+        .trim_start_matches("http://")
+        .trim_start_matches("www.");
+    let query = url.strip_prefix("news.ycombinator.com/item?")?;
+    query
+        .split('#')
+        .next()?
+        .split('&')
+        .find_map(|param| param.strip_prefix("id="))
+        .and_then(|id| id.parse().ok())
 }
 
-pub async fn get_stories(post_ids: &[u32]) -> Result<Vec<Post>, Box<dyn std::error::Error>> {
-    let posts_bodies = get_items(post_ids)
-        .await
-        .into_iter()
-        .filter(|item| match item {
-            Item::Story(_) => true,
-            _ => false,
-        })
-        // Coerse item -> public facing Post struct
-        .map(|item| match item {
-            Item::Story(story) => Post {
-                id: story.id,
-                by: story.by,
-                children: story.kids,
-                title: story.title,
-                time: story.time,
-                url: story.url,
-                text: story.text,
-                descendants: story.descendants,
-            },
-            _ => panic!("Unexpected Item variant"),
-        })
-        .collect::<Vec<Post>>();
+// Coerse item -> public facing Post / Job / Poll struct
+fn to_story(id: u32, item_response: Result<Item, HnError>) -> Result<Story, HnError> {
+    match item_response? {
+        Item::Story(story) => Ok(Story::Post(Post {
+            id: story.id,
+            by: story.by,
+            children: story.kids,
+            title: story.title,
+            time: story.time,
+            url: story.url,
+            text: story.text,
+            descendants: story.descendants,
+            score: story.score,
+            dead: story.dead,
+            deleted: story.deleted,
+        })),
+        Item::Job(job) => Ok(Story::Job(Job {
+            id: job.id,
+            by: job.by,
+            title: job.title,
+            time: job.time,
+            url: job.url,
+            text: job.text,
+            dead: job.dead,
+            deleted: job.deleted,
+        })),
+        Item::Poll(poll) => Ok(Story::Poll(Poll {
+            id: poll.id,
+            by: poll.by,
+            parts: poll.parts,
+            title: poll.title,
+            time: poll.time,
+            text: poll.text,
+            score: poll.score,
+            descendants: poll.descendants,
+            dead: poll.dead,
+            deleted: poll.deleted,
+        })),
+        _ => Err(HnError::UnexpectedItemType {
+            id,
+            expected: "story",
+        }),
+    }
+}
 
-    Ok(posts_bodies)
+fn to_submission(id: u32, item_response: Result<Item, HnError>) -> Result<Submission, HnError> {
+    match item_response {
+        Ok(Item::Comment(comment)) => Ok(Submission::Comment(to_comment(comment))),
+        item_response => to_story(id, item_response).map(Submission::Story),
+    }
 }
 
-pub async fn get_comments(children: &[u32]) -> Result<Vec<Comment>, Box<dyn std::error::Error>> {
-    // TODO add some sort of limit here with children
-    // (i.e. don't load all children if great than x)
-    // will probably need to change the childrens enum
-    let comment_bodies = get_items(children)
-        .await
-        .into_iter()
-        .filter(|item| match item {
-            Item::Comment(_) => true,
-            _ => false,
+// Coerse item -> public facing Comment struct
+fn to_comment(comment: HnComment) -> Comment {
+    Comment {
+        id: comment.id,
+        by: comment.by,
+        children: comment.kids,
+        parent: comment.parent,
+        text: comment.text,
+        time: comment.time,
+        dead: comment.dead,
+        deleted: comment.deleted,
+    }
+}
+
+fn to_comment_result(id: u32, item_response: Result<Item, HnError>) -> Result<Comment, HnError> {
+    match item_response? {
+        Item::Comment(comment) => Ok(to_comment(comment)),
+        _ => Err(HnError::UnexpectedItemType {
+            id,
+            expected: "comment",
+        }),
+    }
+}
+
+// Coerse item -> public facing PollOption struct
+fn to_pollopt(option: HnPollopt) -> PollOption {
+    PollOption {
+        id: option.id,
+        poll: option.poll,
+        text: option.text,
+        score: option.score,
+    }
+}
+
+fn to_poll_option(id: u32, item_response: Result<Item, HnError>) -> Result<PollOption, HnError> {
+    match item_response? {
+        Item::Pollopt(option) => Ok(to_pollopt(option)),
+        _ => Err(HnError::UnexpectedItemType {
+            id,
+            expected: "pollopt",
+        }),
+    }
+}
+
+fn to_any_item(id: u32, item_response: Result<Item, HnError>) -> Result<AnyItem, HnError> {
+    match item_response {
+        Ok(Item::Pollopt(option)) => Ok(AnyItem::PollOption(to_pollopt(option))),
+        item_response => to_submission(id, item_response).map(AnyItem::from),
+    }
+}
+
+// Converts each fetched item, items that failed to load or convert are listed in `failed`
+fn to_batch<T>(
+    item_responses: Vec<(u32, Result<Item, HnError>)>,
+    convert: fn(u32, Result<Item, HnError>) -> Result<T, HnError>,
+) -> BatchResult<T> {
+    let mut result = BatchResult::new();
+    for (id, item_response) in item_responses {
+        match convert(id, item_response) {
+            Ok(item) => result.items.push(item),
+            Err(error) => result.failed.push((id, error)),
+        }
+    }
+    result
+}
+
+// Walks the tree under `parent` breadth first so that the item budget is spent
+// on the shallowest replies first, loading each level with `get_comments`
+async fn crawl_comment_tree<F, Fut>(
+    parent: u32,
+    children: &[u32],
+    limits: &CommentTreeLimits,
+    get_comments: F,
+) -> CommentTree
+where
+    F: Fn(Vec<u32>) -> Fut,
+    Fut: Future<Output = BatchResult<Comment>>,
+{
+    let mut comments: Vec<Comment> = Vec::new();
+    let mut more_replies: Vec<MoreReplies> = Vec::new();
+    let mut failed: Vec<(u32, HnError)> = Vec::new();
+    let mut level: Vec<(u32, Vec<u32>)> = vec![(parent, children.to_vec())];
+    let mut depth = 0;
+
+    while !level.is_empty() {
+        let mut level_ids: Vec<u32> = Vec::new();
+        for (parent, kids) in level {
+            let budget = limits
+                .max_items
+                .saturating_sub(comments.len() + failed.len() + level_ids.len());
+            let n_to_load = if depth < limits.max_depth {
+                kids.len().min(limits.max_children).min(budget)
+            } else {
+                0
+            };
+            level_ids.extend(&kids[..n_to_load]);
+            if n_to_load < kids.len() {
+                more_replies.push(MoreReplies {
+                    parent,
+                    count: kids.len() - n_to_load,
+                });
+            }
+        }
+        if level_ids.is_empty() {
+            break;
+        }
+
+        let level_comments = get_comments(level_ids).await;
+        level = level_comments
+            .items
+            .iter()
+            .filter(|comment| !comment.children.is_empty())
+            .map(|comment| (comment.id, comment.children.clone()))
+            .collect();
+        comments.extend(level_comments.items);
+        failed.extend(level_comments.failed);
+        depth += 1;
+    }
+
+    CommentTree {
+        comments,
+        more_replies,
+        failed,
+    }
+}
+
+// A single pool of connections is used for all requests, including event streams and
+// the HN Search API, so connections are kept alive and reused instead of set up per
+// request. HTTP/2 is negotiated where the server supports it, and responses are gzipped
+// Only connecting is timed out here as event streams stay open indefinitely, requests
+// are timed out on their own
+fn build_http_client(config: &HnClientConfig) -> Result<reqwest::Client, HnError> {
+    let client = reqwest::Client::builder()
+        .user_agent(&config.user_agent)
+        .connect_timeout(config.timeout)
+        .pool_idle_timeout(Duration::from_secs(POOL_IDLE_TIMEOUT_SECS))
+        .pool_max_idle_per_host(config.max_concurrency.max(1))
+        .tcp_keepalive(Duration::from_secs(TCP_KEEPALIVE_SECS))
+        .use_rustls_tls()
+        .gzip(true)
+        .build()?;
+    Ok(client)
+}
+
+impl HnClient {
+    pub fn init(config: HnClientConfig) -> Result<HnClient, HnError> {
+        let client = build_http_client(&config)?;
+        Ok(HnClient {
+            base_url: config.base_url,
+            max_concurrency: config.max_concurrency.max(1),
+            concurrency_limit: AdaptiveLimit::init(
+                config.concurrency,
+                config.min_concurrency,
+                config.max_concurrency,
+            ),
+            timeout: config.timeout,
+            batch_timeout: config.batch_timeout,
+            client,
+            stream_reconnect_delay: config.stream_reconnect_delay,
+            transport: config.transport,
+            retry_policy: RetryPolicy {
+                max_retries: config.max_retries,
+                base_delay: config.retry_base_delay,
+                max_delay: config.retry_max_delay,
+            },
+            rate_limiter: RateLimiter::init(config.requests_per_second),
+            in_flight: SingleFlight::init(),
         })
-        // // Coerse item -> public facing Post struct
-        .map(|item| match item {
-            Item::Comment(comment) => Comment {
-                id: comment.id,
-                by: comment.by,
-                children: comment.kids,
-                parent: comment.parent,
-                text: comment.text,
-                time: comment.time,
+    }
+
+    pub fn transport(&self) -> Transport {
+        self.transport
+    }
+
+    // The number of requests currently allowed in flight
+    pub fn concurrency_limit(&self) -> usize {
+        self.concurrency_limit.current()
+    }
+
+    // The pooled client, for other APIs to share its connections
+    pub fn http_client(&self) -> reqwest::Client {
+        self.client.clone()
+    }
+
+    fn get_route(&self, route: Route) -> String {
+        let path = match route {
+            Route::New => "/newstories.json",
+            Route::Top => "/topstories.json",
+            Route::Best => "/beststories.json",
+            Route::Ask => "/askstories.json",
+            Route::Show => "/showstories.json",
+            Route::Job => "/jobstories.json",
+            Route::Item => "/item",
+            Route::User => "/user",
+            Route::Updates => "/updates.json",
+            Route::MaxItem => "/maxitem.json",
+        };
+        format!("{base_url}{path}", base_url = self.base_url, path = path)
+    }
+
+    fn get_item_route(&self, id: &u32) -> String {
+        let base_url = self.get_route(Route::Item);
+        format!("{base_url}/{id}.json", base_url = base_url, id = id)
+    }
+
+    fn get_user_route(&self, id: &str) -> String {
+        let base_url = self.get_route(Route::User);
+        format!("{base_url}/{id}.json", base_url = base_url, id = id)
+    }
+
+    async fn get_and_jsonify<T>(&self, route: &str) -> Result<T, HnError>
+    where
+        for<'de> T: Deserialize<'de>,
+    {
+        let body = self
+            .in_flight
+            .run(route, || async {
+                self.get_body(route).await.map_err(Arc::new)
+            })
+            .await;
+        match body {
+            Ok(body) => Ok(serde_json::from_slice(&body)?),
+            // The error is only shared if another fetch was waiting on it
+            Err(error) => Err(Arc::try_unwrap(error).unwrap_or_else(HnError::Shared)),
+        }
+    }
+
+    async fn get_body(&self, route: &str) -> Result<Bytes, HnError> {
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            match self.try_get_body(route).await {
+                Ok(body) => return Ok(body),
+                Err(error) if error.is_retryable() && attempts <= self.retry_policy.max_retries => {
+                    delay_for(self.retry_policy.delay(attempts)).await;
+                }
+                Err(error) if attempts > 1 => {
+                    return Err(HnError::Retried {
+                        attempts,
+                        error: Box::new(error),
+                    })
+                }
+                Err(error) => return Err(error),
+            }
+        }
+    }
+
+    async fn try_get_body(&self, route: &str) -> Result<Bytes, HnError> {
+        let permit = self.concurrency_limit.acquire().await;
+        self.rate_limiter.acquire().await;
+        let started = Instant::now();
+        let request = async {
+            let response = self.client.get(route).send().await?;
+            let status = response.status();
+            if !status.is_success() {
+                return Err(HnError::Status(status));
+            }
+            Ok(response.bytes().await?)
+        };
+        let result = match timeout(self.timeout, request).await {
+            Ok(result) => result,
+            Err(_) => Err(HnError::Timeout(self.timeout)),
+        };
+        permit.finish(match &result {
+            Ok(_) => Outcome::Success(started.elapsed()),
+            Err(error) if error.is_retryable() => Outcome::Overloaded,
+            Err(_) => Outcome::Ignored,
+        });
+        result
+    }
+
+    // Items not fetched by `deadline` fail with a timeout
+    async fn get_item_before(&self, id: u32, deadline: Instant) -> Result<Item, HnError> {
+        match timeout_at(deadline.into(), self.get_hn_item(id)).await {
+            Ok(item_response) => item_response,
+            Err(_) => Err(HnError::Timeout(self.batch_timeout)),
+        }
+    }
+
+    async fn get_hn_item(&self, id: u32) -> Result<Item, HnError> {
+        let route = self.get_item_route(&id);
+        // Items that do not exist are returned as `null`
+        match self.get_and_jsonify::<Option<Item>>(&route).await? {
+            Some(item) => Ok(item),
+            None => Err(HnError::NullItem(id)),
+        }
+    }
+
+    // Items are returned in the same order as `ids`
+    async fn get_items(&self, ids: &[u32]) -> Vec<(u32, Result<Item, HnError>)> {
+        let deadline = Instant::now() + self.batch_timeout;
+        stream::iter(ids.iter().cloned())
+            .map(|item_id| async move { (item_id, self.get_item_before(item_id, deadline).await) })
+            .buffered(self.max_concurrency)
+            .collect::<Vec<(u32, Result<Item, HnError>)>>()
+            .await
+    }
+
+    fn get_post_ids_route(&self, story_type: &StoryListType) -> String {
+        match story_type {
+            StoryListType::Top => self.get_route(Route::Top),
+            StoryListType::Best => self.get_route(Route::Best),
+            StoryListType::New => self.get_route(Route::New),
+            StoryListType::Ask => self.get_route(Route::Ask),
+            StoryListType::Show => self.get_route(Route::Show),
+            StoryListType::Job => self.get_route(Route::Job),
+        }
+    }
+
+    pub async fn get_post_ids(&self, story_type: &StoryListType) -> Result<Vec<u32>, HnError> {
+        let route = self.get_post_ids_route(story_type);
+        let post_ids = self.get_and_jsonify::<Vec<u32>>(&route).await?;
+        Ok(post_ids)
+    }
+
+    // Streams the story list each time it changes
+    pub fn watch_post_ids(
+        &self,
+        story_type: &StoryListType,
+    ) -> impl Stream<Item = Result<Vec<u32>, HnError>> {
+        let route = self.get_post_ids_route(story_type);
+        event_stream::watch(
+            self.client.clone(),
+            route,
+            self.stream_reconnect_delay,
+            |value| {
+                // Removed entries are left as `null` until the next full put
+                let post_ids = serde_json::from_value::<Option<Vec<Option<u32>>>>(value.clone())?;
+                Ok(post_ids.unwrap_or_default().into_iter().flatten().collect())
+            },
+        )
+    }
+
+    // Streams a story or comment each time it changes
+    pub fn watch_item(&self, id: u32) -> impl Stream<Item = Result<Submission, HnError>> {
+        let route = self.get_item_route(&id);
+        event_stream::watch(
+            self.client.clone(),
+            route,
+            self.stream_reconnect_delay,
+            move |value| {
+                let item_response = match serde_json::from_value::<Option<Item>>(value.clone())? {
+                    Some(item) => Ok(item),
+                    None => Err(HnError::NullItem(id)),
+                };
+                to_submission(id, item_response)
             },
-            _ => panic!("Unexpected Item variant"),
+        )
+    }
+
+    pub async fn get_updates(&self) -> Result<Updates, HnError> {
+        let route = self.get_route(Route::Updates);
+        self.get_and_jsonify::<Updates>(&route).await
+    }
+
+    // The id of the most recently created item
+    pub async fn get_max_item(&self) -> Result<u32, HnError> {
+        let route = self.get_route(Route::MaxItem);
+        self.get_and_jsonify::<u32>(&route).await
+    }
+
+    // An item of any kind, e.g. one opened by id
+    pub async fn get_item(&self, id: u32) -> Result<AnyItem, HnError> {
+        to_any_item(id, self.get_hn_item(id).await)
+    }
+
+    // Yields each story as soon as it has loaded, along with its index in `post_ids`
+    pub fn stream_stories<'a>(
+        &'a self,
+        post_ids: &'a [u32],
+    ) -> impl Stream<Item = (usize, Result<Story, HnError>)> + 'a {
+        let deadline = Instant::now() + self.batch_timeout;
+        stream::iter(post_ids.iter().cloned().enumerate())
+            .map(move |(index, post_id)| async move {
+                let item_response = self.get_item_before(post_id, deadline).await;
+                (index, to_story(post_id, item_response))
+            })
+            .buffer_unordered(self.max_concurrency)
+    }
+
+    // Fetches the options of a poll, i.e. the poll's `parts`
+    pub async fn get_poll_options(&self, part_ids: &[u32]) -> BatchResult<PollOption> {
+        to_batch(self.get_items(part_ids).await, to_poll_option)
+    }
+
+    pub async fn get_comments(&self, children: &[u32]) -> BatchResult<Comment> {
+        to_batch(self.get_items(children).await, to_comment_result)
+    }
+
+    pub async fn get_user(&self, id: &str) -> Result<User, HnError> {
+        let route = self.get_user_route(id);
+        match self.get_and_jsonify::<Option<HnUser>>(&route).await? {
+            Some(user) => Ok(User {
+                id: user.id,
+                created: user.created,
+                karma: user.karma,
+                about: user.about,
+                submitted: user.submitted,
+            }),
+            None => Err(HnError::NullUser(id.to_string())),
+        }
+    }
+
+    // A user's submissions can be any kind of item, poll options are not supported
+    pub async fn get_submissions(&self, ids: &[u32]) -> BatchResult<Submission> {
+        to_batch(self.get_items(ids).await, to_submission)
+    }
+
+    pub async fn get_comment_tree(
+        &self,
+        parent: u32,
+        children: &[u32],
+        limits: &CommentTreeLimits,
+    ) -> CommentTree {
+        crawl_comment_tree(parent, children, limits, |ids| async move {
+            self.get_comments(&ids).await
         })
-        .collect::<Vec<Comment>>();
+        .await
+    }
+}
 
-    Ok(comment_bodies)
+impl HnBackend for HnClient {
+    fn get_post_ids<'a>(
+        &'a self,
+        story_type: &'a StoryListType,
+    ) -> BoxFuture<'a, Result<Vec<u32>, HnError>> {
+        HnClient::get_post_ids(self, story_type).boxed()
+    }
+
+    fn stream_stories<'a>(
+        &'a self,
+        post_ids: &'a [u32],
+    ) -> BoxStream<'a, (usize, Result<Story, HnError>)> {
+        HnClient::stream_stories(self, post_ids).boxed()
+    }
+
+    fn get_submissions<'a>(&'a self, ids: &'a [u32]) -> BoxFuture<'a, BatchResult<Submission>> {
+        HnClient::get_submissions(self, ids).boxed()
+    }
+
+    fn get_poll_options<'a>(&'a self, ids: &'a [u32]) -> BoxFuture<'a, BatchResult<PollOption>> {
+        HnClient::get_poll_options(self, ids).boxed()
+    }
+
+    fn get_item(&self, id: u32) -> BoxFuture<'_, Result<AnyItem, HnError>> {
+        HnClient::get_item(self, id).boxed()
+    }
+
+    fn get_user<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Result<User, HnError>> {
+        HnClient::get_user(self, id).boxed()
+    }
+
+    fn get_comment_tree<'a>(
+        &'a self,
+        parent: u32,
+        children: &'a [u32],
+        limits: &'a CommentTreeLimits,
+    ) -> BoxFuture<'a, CommentTree> {
+        HnClient::get_comment_tree(self, parent, children, limits).boxed()
+    }
+
+    fn get_updates(&self) -> BoxFuture<'_, Result<Updates, HnError>> {
+        HnClient::get_updates(self).boxed()
+    }
+
+    fn get_max_item(&self) -> BoxFuture<'_, Result<Option<u32>, HnError>> {
+        HnClient::get_max_item(self)
+            .map(|max_item| max_item.map(Some))
+            .boxed()
+    }
+
+    fn concurrency_limit(&self) -> Option<usize> {
+        Some(HnClient::concurrency_limit(self))
+    }
 }
 
 #[cfg(test)]
@@ -187,16 +1006,44 @@ mod tests {
     use mockito::mock;
     use rand::distributions::Alphanumeric;
     use rand::Rng;
-    use serde_json;
     use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    fn make_client() -> HnClient {
+        HnClient::init(HnClientConfig {
+            base_url: mockito::server_url(),
+            max_retries: 0,
+            ..HnClientConfig::default()
+        })
+        .unwrap()
+    }
+
+    // Stories are returned in the same order as `post_ids`
+    async fn get_stories(client: &HnClient, post_ids: &[u32]) -> BatchResult<Story> {
+        to_batch(client.get_items(post_ids).await, to_story)
+    }
+
+    fn make_retrying_client(max_retries: u32) -> HnClient {
+        HnClient::init(HnClientConfig {
+            base_url: mockito::server_url(),
+            max_retries,
+            retry_base_delay: Duration::from_millis(1),
+            ..HnClientConfig::default()
+        })
+        .unwrap()
+    }
 
     fn make_mock_story(id: u32) -> Item {
         let mut rng = rand::thread_rng();
         Item::Story(HnStory {
-            id: id,
+            id,
+            dead: false,
+            deleted: false,
             descendants: 5,
             by: rng.sample_iter(&Alphanumeric).take(10).collect(),
-            kids: vec![0; 5].iter().map(|_| rng.gen()).collect(),
+            kids: [0; 5].iter().map(|_| rng.gen()).collect(),
             text: Some(rng.sample_iter(&Alphanumeric).take(30).collect()),
             score: rng.gen(),
             time: rng.gen(),
@@ -208,14 +1055,35 @@ mod tests {
     fn make_mock_comment(id: u32, parent: u32) -> Item {
         let mut rng = rand::thread_rng();
         Item::Comment(HnComment {
-            id: id,
-            by: rng.sample_iter(&Alphanumeric).take(10).collect(),
-            kids: vec![0; 5].iter().map(|_| rng.gen()).collect(),
-            parent: parent,
-            text: rng.sample_iter(&Alphanumeric).take(30).collect(),
+            id,
+            by: Some(rng.sample_iter(&Alphanumeric).take(10).collect()),
+            dead: false,
+            deleted: false,
+            kids: [0; 5].iter().map(|_| rng.gen()).collect(),
+            parent,
+            text: Some(rng.sample_iter(&Alphanumeric).take(30).collect()),
             time: rng.gen(),
         })
     }
+    #[test]
+    fn item_ids_are_parsed_from_ids_and_urls() {
+        assert_eq!(parse_item_id(" 8863 "), Some(8863));
+        assert_eq!(
+            parse_item_id("https://news.ycombinator.com/item?id=8863"),
+            Some(8863)
+        );
+        assert_eq!(
+            parse_item_id("news.ycombinator.com/item?p=2&id=8863#8870"),
+            Some(8863)
+        );
+        assert_eq!(
+            parse_item_id("https://news.ycombinator.com/user?id=pg"),
+            None
+        );
+        assert_eq!(parse_item_id("https://example.com/item?id=8863"), None);
+        assert_eq!(parse_item_id("item"), None);
+    }
+
     #[tokio::test]
     async fn get_top_post_ids() {
         let story_ids: Vec<u32> = (0..30).collect();
@@ -227,11 +1095,39 @@ mod tests {
             .expect(1)
             .create();
 
-        let post_ids = get_post_ids(&StoryListType::Top).await.unwrap();
+        let post_ids = make_client()
+            .get_post_ids(&StoryListType::Top)
+            .await
+            .unwrap();
         get_top_stories_mock.assert();
         assert_eq!(post_ids.len(), story_ids.len());
     }
 
+    #[tokio::test]
+    async fn get_post_ids_for_each_list() {
+        let routes = [
+            (StoryListType::Top, "/topstories.json"),
+            (StoryListType::New, "/newstories.json"),
+            (StoryListType::Best, "/beststories.json"),
+            (StoryListType::Ask, "/askstories.json"),
+            (StoryListType::Show, "/showstories.json"),
+            (StoryListType::Job, "/jobstories.json"),
+        ];
+        let client = make_client();
+        for (story_type, route) in routes.iter() {
+            let list_mock = mock("GET", *route)
+                .with_status(200)
+                .with_header("content-type", "application/json")
+                .with_body("[1, 2]")
+                .expect(1)
+                .create();
+
+            let post_ids = client.get_post_ids(story_type).await.unwrap();
+            list_mock.assert();
+            assert_eq!(post_ids, vec![1, 2]);
+        }
+    }
+
     #[tokio::test]
     async fn get_top_stories_returns_posts() {
         let story_ids: Vec<u32> = (0..30).collect();
@@ -242,7 +1138,7 @@ mod tests {
             .iter()
             .cloned()
             .map(|id| {
-                let route = format!("/item/{}.json", id).to_owned();
+                let route = format!("/item/{}.json", id);
                 let mock_story = make_mock_story(id);
                 let raw_story = serde_json::to_string(&mock_story).unwrap();
                 let expected_calls = if id >= skip as u32 && id < (skip + limit) as u32 {
@@ -257,18 +1153,24 @@ mod tests {
                     .expect(expected_calls)
                     .create();
 
-                return (id, (mock_story, mock_inst));
+                (id, (mock_story, mock_inst))
             })
             .collect();
 
-        let stories_result = get_stories(&story_ids[skip..skip + limit]).await.unwrap();
+        let stories_result = get_stories(&make_client(), &story_ids[skip..skip + limit])
+            .await
+            .items;
         assert_eq!(stories_result.len(), limit);
 
-        for post in stories_result {
+        for story in stories_result {
+            let post = match story {
+                Story::Post(post) => post,
+                _ => panic!("Unexpected Story variant"),
+            };
             let id = post.id;
             let (item_mock, mock_inst) = mock_stories.get(&id).unwrap();
             // Assert pagination params respected
-            assert_eq!(true, id >= skip as u32 && id < (skip + limit) as u32);
+            assert!(id >= skip as u32 && id < (skip + limit) as u32);
             mock_inst.assert();
 
             match item_mock {
@@ -285,8 +1187,8 @@ mod tests {
     async fn get_comments_returns_comments() {
         let mut rng = rand::thread_rng();
         let parent_id: u32 = rng.gen();
-        let comment_ids_to_get: Vec<u32> = vec![0; 5].iter().map(|_| rng.gen()).collect();
-        let comment_ids_to_not_get: Vec<u32> = vec![0; 5].iter().map(|_| rng.gen()).collect();
+        let comment_ids_to_get: Vec<u32> = [0; 5].iter().map(|_| rng.gen()).collect();
+        let comment_ids_to_not_get: Vec<u32> = [0; 5].iter().map(|_| rng.gen()).collect();
         let all_comment_ids: Vec<u32> =
             [&comment_ids_to_get[..], &comment_ids_to_not_get[..]].concat();
 
@@ -294,7 +1196,7 @@ mod tests {
             .iter()
             .cloned()
             .map(|id| {
-                let route = format!("/item/{}.json", id).to_owned();
+                let route = format!("/item/{}.json", id);
                 let mock_comment = make_mock_comment(id, parent_id);
                 let raw_comment = serde_json::to_string(&mock_comment).unwrap();
                 // Yes this is O(n2) - however this is a small number
@@ -310,11 +1212,11 @@ mod tests {
                     .expect(expected_calls)
                     .create();
 
-                return (id, (mock_comment, mock_inst));
+                (id, (mock_comment, mock_inst))
             })
             .collect();
 
-        let comments_result = get_comments(&comment_ids_to_get).await.unwrap();
+        let comments_result = make_client().get_comments(&comment_ids_to_get).await.items;
         assert_eq!(comments_result.len(), comment_ids_to_get.len());
         for comment in comments_result {
             let (item_mock, mock_inst) = mock_comments.get(&comment.id).unwrap();
@@ -328,4 +1230,796 @@ mod tests {
             }
         }
     }
+
+    fn mock_comment_route(
+        id: u32,
+        parent: u32,
+        kids: Vec<u32>,
+        expected_calls: usize,
+    ) -> mockito::Mock {
+        let mut rng = rand::thread_rng();
+        let comment = Item::Comment(HnComment {
+            id,
+            by: Some(rng.sample_iter(&Alphanumeric).take(10).collect()),
+            dead: false,
+            deleted: false,
+            kids,
+            parent,
+            text: Some(rng.sample_iter(&Alphanumeric).take(30).collect()),
+            time: rng.gen(),
+        });
+        mock("GET", &*format!("/item/{}.json", id))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(serde_json::to_string(&comment).unwrap())
+            .expect(expected_calls)
+            .create()
+    }
+
+    #[tokio::test]
+    async fn get_comment_tree_respects_limits() {
+        // 1 -> [10 -> [100 -> [1000]], 11, 12], 2, 3
+        let mocks = vec![
+            mock_comment_route(1, 0, vec![10, 11, 12], 1),
+            mock_comment_route(2, 0, vec![], 1),
+            mock_comment_route(3, 0, vec![], 0),
+            mock_comment_route(10, 1, vec![100], 1),
+            mock_comment_route(11, 1, vec![], 1),
+            mock_comment_route(12, 1, vec![], 0),
+            mock_comment_route(100, 10, vec![1000], 1),
+            mock_comment_route(1000, 100, vec![], 0),
+        ];
+        let limits = CommentTreeLimits {
+            max_depth: 3,
+            max_children: 2,
+            max_items: 10,
+        };
+
+        let tree = make_client().get_comment_tree(0, &[1, 2, 3], &limits).await;
+        for mock_inst in mocks {
+            mock_inst.assert();
+        }
+        let mut comment_ids: Vec<u32> = tree.comments.iter().map(|comment| comment.id).collect();
+        comment_ids.sort();
+        assert_eq!(comment_ids, vec![1, 2, 10, 11, 100]);
+
+        let mut more_replies: Vec<(u32, usize)> = tree
+            .more_replies
+            .iter()
+            .map(|more| (more.parent, more.count))
+            .collect();
+        more_replies.sort();
+        assert_eq!(more_replies, vec![(0, 1), (1, 1), (100, 1)]);
+    }
+
+    #[tokio::test]
+    async fn get_comment_tree_respects_item_budget() {
+        let mocks = vec![
+            mock_comment_route(1, 0, vec![10, 11], 1),
+            mock_comment_route(2, 0, vec![], 1),
+            mock_comment_route(10, 1, vec![], 1),
+            mock_comment_route(11, 1, vec![], 0),
+        ];
+        let limits = CommentTreeLimits {
+            max_depth: 5,
+            max_children: 5,
+            max_items: 3,
+        };
+
+        let tree = make_client().get_comment_tree(0, &[1, 2], &limits).await;
+        for mock_inst in mocks {
+            mock_inst.assert();
+        }
+        assert_eq!(tree.comments.len(), 3);
+        assert_eq!(tree.more_replies.len(), 1);
+        assert_eq!(tree.more_replies[0].parent, 1);
+        assert_eq!(tree.more_replies[0].count, 1);
+    }
+
+    #[tokio::test]
+    async fn client_uses_configured_user_agent() {
+        let user_agent_mock = mock("GET", "/topstories.json")
+            .match_header("user-agent", "hn-test-agent")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body("[1, 2, 3]")
+            .expect(1)
+            .create();
+
+        let client = HnClient::init(HnClientConfig {
+            base_url: mockito::server_url(),
+            user_agent: String::from("hn-test-agent"),
+            ..HnClientConfig::default()
+        })
+        .unwrap();
+        let post_ids = client.get_post_ids(&StoryListType::Top).await.unwrap();
+        user_agent_mock.assert();
+        assert_eq!(post_ids, vec![1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn get_stories_reports_failed_items() {
+        let ok_mock = mock("GET", "/item/1.json")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(serde_json::to_string(&make_mock_story(1)).unwrap())
+            .create();
+        let status_mock = mock("GET", "/item/2.json").with_status(500).create();
+        let null_mock = mock("GET", "/item/3.json")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body("null")
+            .create();
+        let decode_mock = mock("GET", "/item/4.json")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body("{\"type\": \"story\"")
+            .create();
+        let comment_mock = mock("GET", "/item/5.json")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(serde_json::to_string(&make_mock_comment(5, 1)).unwrap())
+            .create();
+
+        let result = get_stories(&make_client(), &[1, 2, 3, 4, 5]).await;
+        for mock_inst in [ok_mock, status_mock, null_mock, decode_mock, comment_mock].iter() {
+            mock_inst.assert();
+        }
+        assert_eq!(result.items.len(), 1);
+        assert!(matches!(&result.items[0], Story::Post(post) if post.id == 1));
+
+        let mut failed = result.failed;
+        failed.sort_by_key(|(id, _)| *id);
+        let failed_ids: Vec<u32> = failed.iter().map(|(id, _)| *id).collect();
+        assert_eq!(failed_ids, vec![2, 3, 4, 5]);
+        assert!(matches!(failed[0].1, HnError::Status(status) if status.as_u16() == 500));
+        assert!(matches!(failed[1].1, HnError::NullItem(3)));
+        assert!(matches!(failed[2].1, HnError::Decode(_)));
+        assert!(matches!(
+            failed[3].1,
+            HnError::UnexpectedItemType {
+                id: 5,
+                expected: "story"
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn get_stories_returns_jobs() {
+        let job = Item::Job(HnJob {
+            by: String::from("company"),
+            dead: false,
+            deleted: false,
+            id: 7,
+            score: 1,
+            text: None,
+            time: 0,
+            title: String::from("Company is hiring"),
+            url: Some(String::from("https://example.com/jobs")),
+        });
+        let job_mock = mock("GET", "/item/7.json")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(serde_json::to_string(&job).unwrap())
+            .expect(1)
+            .create();
+
+        let result = get_stories(&make_client(), &[7]).await;
+        job_mock.assert();
+        assert!(result.failed.is_empty());
+        match &result.items[..] {
+            [Story::Job(job)] => {
+                assert_eq!(job.title, "Company is hiring");
+                assert_eq!(job.url.as_deref(), Some("https://example.com/jobs"));
+            }
+            _ => panic!("Unexpected Story variant"),
+        }
+    }
+
+    #[tokio::test]
+    async fn get_poll_and_options() {
+        let poll = Item::Poll(HnPoll {
+            by: String::from("author"),
+            dead: false,
+            deleted: false,
+            descendants: 0,
+            id: 10,
+            kids: vec![],
+            parts: vec![11, 12],
+            score: 5,
+            text: None,
+            time: 0,
+            title: String::from("Which option?"),
+        });
+        let poll_mock = mock("GET", "/item/10.json")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(serde_json::to_string(&poll).unwrap())
+            .expect(1)
+            .create();
+        let option_mocks: Vec<mockito::Mock> = [(11, 7), (12, 3)]
+            .iter()
+            .map(|(id, score)| {
+                let option = Item::Pollopt(HnPollopt {
+                    by: String::from("author"),
+                    id: *id,
+                    poll: 10,
+                    score: *score,
+                    text: format!("Option {}", id),
+                    time: 0,
+                });
+                mock("GET", &*format!("/item/{}.json", id))
+                    .with_status(200)
+                    .with_header("content-type", "application/json")
+                    .with_body(serde_json::to_string(&option).unwrap())
+                    .expect(1)
+                    .create()
+            })
+            .collect();
+
+        let client = make_client();
+        let stories = get_stories(&client, &[10]).await;
+        poll_mock.assert();
+        let parts = match &stories.items[..] {
+            [Story::Poll(poll)] => poll.parts.clone(),
+            _ => panic!("Unexpected Story variant"),
+        };
+
+        let options = client.get_poll_options(&parts).await;
+        for mock_inst in option_mocks {
+            mock_inst.assert();
+        }
+        assert!(options.failed.is_empty());
+        let mut scores: Vec<(u32, u16)> = options
+            .items
+            .iter()
+            .map(|option| (option.id, option.score))
+            .collect();
+        scores.sort();
+        assert_eq!(scores, vec![(11, 7), (12, 3)]);
+    }
+
+    #[tokio::test]
+    async fn get_item_returns_any_kind() {
+        let option = Item::Pollopt(HnPollopt {
+            by: String::from("author"),
+            id: 21,
+            poll: 20,
+            score: 1,
+            text: String::from("Option"),
+            time: 0,
+        });
+        let item_mocks = vec![
+            mock("GET", "/item/21.json")
+                .with_status(200)
+                .with_header("content-type", "application/json")
+                .with_body(serde_json::to_string(&option).unwrap())
+                .expect(1)
+                .create(),
+            mock("GET", "/item/22.json")
+                .with_status(200)
+                .with_header("content-type", "application/json")
+                .with_body(serde_json::to_string(&make_mock_comment(22, 20)).unwrap())
+                .expect(1)
+                .create(),
+            mock("GET", "/item/23.json")
+                .with_status(200)
+                .with_header("content-type", "application/json")
+                .with_body(serde_json::to_string(&make_mock_story(23)).unwrap())
+                .expect(1)
+                .create(),
+        ];
+
+        let client = make_client();
+        let option = client.get_item(21).await;
+        let comment = client.get_item(22).await;
+        let story = client.get_item(23).await;
+        for mock_inst in item_mocks {
+            mock_inst.assert();
+        }
+        assert!(matches!(
+            option,
+            Ok(AnyItem::PollOption(PollOption { poll: 20, .. }))
+        ));
+        assert!(matches!(
+            comment,
+            Ok(AnyItem::Comment(Comment { parent: 20, .. }))
+        ));
+        assert!(matches!(story, Ok(AnyItem::Post(Post { id: 23, .. }))));
+    }
+
+    #[tokio::test]
+    async fn get_items_tolerates_missing_fields() {
+        // Shapes returned by the API for a story without comments and for
+        // deleted / dead comments
+        let bodies = [
+            (
+                20,
+                r#"{"by":"author","descendants":0,"id":20,"score":1,"time":1,"title":"No comments","type":"story","url":"https://example.com"}"#,
+            ),
+            (
+                21,
+                r#"{"deleted":true,"id":21,"kids":[23],"parent":20,"time":1,"type":"comment"}"#,
+            ),
+            (
+                22,
+                r#"{"by":"author","dead":true,"id":22,"parent":20,"time":1,"type":"comment"}"#,
+            ),
+        ];
+        let mocks: Vec<mockito::Mock> = bodies
+            .iter()
+            .map(|(id, body)| {
+                mock("GET", &*format!("/item/{}.json", id))
+                    .with_status(200)
+                    .with_header("content-type", "application/json")
+                    .with_body(*body)
+                    .expect(1)
+                    .create()
+            })
+            .collect();
+
+        let client = make_client();
+        let stories = get_stories(&client, &[20]).await;
+        let comments = client.get_comments(&[21, 22]).await;
+        for mock_inst in mocks {
+            mock_inst.assert();
+        }
+
+        assert!(stories.failed.is_empty());
+        match &stories.items[..] {
+            [Story::Post(post)] => assert!(post.children.is_empty()),
+            _ => panic!("Unexpected Story variant"),
+        }
+
+        assert!(comments.failed.is_empty());
+        let mut comments = comments.items;
+        comments.sort_by_key(|comment| comment.id);
+        assert!(comments[0].deleted);
+        assert_eq!(comments[0].by, None);
+        assert_eq!(comments[0].children, vec![23]);
+        assert!(comments[1].dead);
+        assert_eq!(comments[1].text, None);
+    }
+
+    #[test]
+    fn deleted_stories_polls_and_jobs_decode() {
+        let story = serde_json::from_str(r#"{"id":1,"deleted":true,"type":"story","time":1}"#);
+        match to_story(1, story.map_err(HnError::Decode)) {
+            Ok(Story::Post(post)) => {
+                assert!(post.deleted);
+                assert_eq!(post.title, "");
+                assert!(post.children.is_empty());
+            }
+            _ => panic!("Unexpected Story variant"),
+        }
+
+        let poll = serde_json::from_str(r#"{"id":2,"deleted":true,"type":"poll","time":1}"#);
+        match to_story(2, poll.map_err(HnError::Decode)) {
+            Ok(Story::Poll(poll)) => {
+                assert!(poll.deleted);
+                assert!(poll.parts.is_empty());
+            }
+            _ => panic!("Unexpected Story variant"),
+        }
+
+        let job = serde_json::from_str(r#"{"id":3,"deleted":true,"type":"job","time":1}"#);
+        match to_story(3, job.map_err(HnError::Decode)) {
+            Ok(Story::Job(job)) => {
+                assert!(job.deleted);
+                assert_eq!(job.by, "");
+                assert_eq!(job.title, "");
+            }
+            _ => panic!("Unexpected Story variant"),
+        }
+    }
+
+    fn mock_story_routes(ids: &[u32]) -> Vec<mockito::Mock> {
+        ids.iter()
+            .map(|id| {
+                mock("GET", &*format!("/item/{}.json", id))
+                    .with_status(200)
+                    .with_header("content-type", "application/json")
+                    .with_body(serde_json::to_string(&make_mock_story(*id)).unwrap())
+                    .expect(1)
+                    .create()
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn get_items_preserves_order() {
+        let post_ids: Vec<u32> = vec![9, 3, 7, 1, 8, 2, 6, 4, 5, 0];
+        let mocks = mock_story_routes(&post_ids);
+
+        let stories = get_stories(&make_client(), &post_ids).await;
+        for mock_inst in mocks {
+            mock_inst.assert();
+        }
+        let story_ids: Vec<u32> = stories
+            .items
+            .iter()
+            .map(|story| match story {
+                Story::Post(post) => post.id,
+                _ => panic!("Unexpected Story variant"),
+            })
+            .collect();
+        assert_eq!(story_ids, post_ids);
+    }
+
+    #[tokio::test]
+    async fn concurrent_fetches_share_requests() {
+        let post_ids: Vec<u32> = vec![1, 2, 3];
+        let mut mocks = mock_story_routes(&post_ids);
+        mocks.push(
+            mock("GET", "/topstories.json")
+                .with_status(200)
+                .with_header("content-type", "application/json")
+                .with_body(serde_json::to_string(&post_ids).unwrap())
+                .expect(1)
+                .create(),
+        );
+        mocks.push(
+            mock("GET", "/item/4.json")
+                .with_status(500)
+                .expect(1)
+                .create(),
+        );
+
+        let client = make_client();
+        // A page load and a background fetch asking for the same list and items
+        let (stories, submissions, first_ids, second_ids) = futures::join!(
+            get_stories(&client, &[1, 2, 3, 4]),
+            client.get_submissions(&[3, 4, 2, 1]),
+            client.get_post_ids(&StoryListType::Top),
+            client.get_post_ids(&StoryListType::Top),
+        );
+        for mock_inst in mocks {
+            mock_inst.assert();
+        }
+        assert_eq!(stories.items.len(), 3);
+        assert_eq!(submissions.items.len(), 3);
+        assert_eq!(first_ids.unwrap(), post_ids);
+        assert_eq!(second_ids.unwrap(), post_ids);
+        // Both fetches see the failed request's error
+        for failed in [&stories.failed, &submissions.failed].iter() {
+            assert_eq!(failed.len(), 1);
+            assert!(failed[0].1.to_string().contains("500"));
+        }
+
+        // Requests are only shared while in flight
+        let refetch_mock = mock("GET", "/item/1.json")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(serde_json::to_string(&make_mock_story(1)).unwrap())
+            .expect(1)
+            .create();
+        get_stories(&client, &[1]).await;
+        refetch_mock.assert();
+    }
+
+    #[tokio::test]
+    async fn stream_stories_yields_indexes() {
+        let post_ids: Vec<u32> = vec![4, 2, 3, 1];
+        let mocks = mock_story_routes(&post_ids);
+
+        let client = make_client();
+        let mut indexes: Vec<usize> = client
+            .stream_stories(&post_ids)
+            .map(|(index, story)| {
+                match story {
+                    Ok(Story::Post(post)) => assert_eq!(post.id, post_ids[index]),
+                    _ => panic!("Unexpected Story variant"),
+                }
+                index
+            })
+            .collect()
+            .await;
+        for mock_inst in mocks {
+            mock_inst.assert();
+        }
+        indexes.sort();
+        assert_eq!(indexes, vec![0, 1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn get_user_and_submissions() {
+        let user_mock = mock("GET", "/user/pg.json")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{"about":"Bug fixer.","created":1160418092,"id":"pg","karma":155111,"submitted":[30, 31]}"#,
+            )
+            .expect(1)
+            .create();
+        let missing_user_mock = mock("GET", "/user/nobody.json")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body("null")
+            .expect(1)
+            .create();
+        let mut submission_mocks = mock_story_routes(&[30]);
+        submission_mocks.push(mock_comment_route(31, 30, vec![], 1));
+
+        let client = make_client();
+        let user = client.get_user("pg").await.unwrap();
+        user_mock.assert();
+        assert_eq!(user.karma, 155111);
+        assert_eq!(user.about.as_deref(), Some("Bug fixer."));
+
+        let missing_user = client.get_user("nobody").await;
+        missing_user_mock.assert();
+        assert!(matches!(missing_user, Err(HnError::NullUser(_))));
+
+        let submissions = client.get_submissions(&user.submitted).await;
+        for mock_inst in submission_mocks {
+            mock_inst.assert();
+        }
+        assert!(submissions.failed.is_empty());
+        assert!(matches!(
+            &submissions.items[..],
+            [Submission::Story(Story::Post(_)), Submission::Comment(_)]
+        ));
+    }
+
+    #[tokio::test]
+    async fn get_updates_and_max_item() {
+        let updates_mock = mock("GET", "/updates.json")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"items":[8423305,8420805],"profiles":["thefox","mdda"]}"#)
+            .expect(1)
+            .create();
+        let max_item_mock = mock("GET", "/maxitem.json")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body("8423305")
+            .expect(1)
+            .create();
+
+        let client = make_client();
+        let updates = client.get_updates().await.unwrap();
+        let max_item = client.get_max_item().await.unwrap();
+        updates_mock.assert();
+        max_item_mock.assert();
+        assert_eq!(updates.items, vec![8423305, 8420805]);
+        assert_eq!(updates.profiles, vec!["thefox", "mdda"]);
+        assert_eq!(max_item, 8423305);
+    }
+
+    #[tokio::test]
+    async fn failed_requests_are_retried() {
+        // mockito serves the first matching mock that is still expecting hits,
+        // so the failures are served before the success
+        let failed_mock = mock("GET", "/topstories.json")
+            .with_status(503)
+            .expect(2)
+            .create();
+        let ok_mock = mock("GET", "/topstories.json")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body("[1, 2]")
+            .expect(1)
+            .create();
+
+        let post_ids = make_retrying_client(2)
+            .get_post_ids(&StoryListType::Top)
+            .await
+            .unwrap();
+        failed_mock.assert();
+        ok_mock.assert();
+        assert_eq!(post_ids, vec![1, 2]);
+    }
+
+    #[tokio::test]
+    async fn retries_are_reported_when_exhausted() {
+        let failed_mock = mock("GET", "/item/1.json")
+            .with_status(500)
+            .expect(3)
+            .create();
+        // Client errors are not retried
+        let missing_mock = mock("GET", "/item/2.json")
+            .with_status(404)
+            .expect(1)
+            .create();
+
+        let client = make_retrying_client(2);
+        let failed = client.get_item(1).await;
+        let missing = client.get_item(2).await;
+        failed_mock.assert();
+        missing_mock.assert();
+        match failed {
+            Err(HnError::Retried { attempts, error }) => {
+                assert_eq!(attempts, 3);
+                assert!(matches!(*error, HnError::Status(status) if status.as_u16() == 500));
+            }
+            _ => panic!("Expected retried error"),
+        }
+        assert!(matches!(missing, Err(HnError::Status(status)) if status.as_u16() == 404));
+    }
+
+    // Accepts connections and never responds to them
+    async fn serve_nothing() -> String {
+        let mut listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let mut sockets = Vec::new();
+            while let Ok((socket, _)) = listener.accept().await {
+                sockets.push(socket);
+            }
+        });
+        url
+    }
+
+    // Serves every item as a comment over keep-alive connections, counting the
+    // connections opened
+    async fn serve_items(connections: Arc<AtomicUsize>) -> String {
+        let mut listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                connections.fetch_add(1, Ordering::SeqCst);
+                tokio::spawn(async move {
+                    let mut request = Vec::new();
+                    let mut buffer = [0; 1024];
+                    loop {
+                        let read = match socket.read(&mut buffer).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(read) => read,
+                        };
+                        request.extend_from_slice(&buffer[..read]);
+                        // Requests have no body so each ends with a blank line
+                        while let Some(end) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+                            let head: Vec<u8> = request.drain(..end + 4).collect();
+                            let head = String::from_utf8_lossy(&head);
+                            let id: u32 = head
+                                .split_whitespace()
+                                .nth(1)
+                                .unwrap_or_default()
+                                .trim_start_matches("/item/")
+                                .trim_end_matches(".json")
+                                .parse()
+                                .unwrap_or(0);
+                            let body = format!(
+                                r#"{{"by":"pg","id":{},"parent":1,"time":0,"type":"comment"}}"#,
+                                id
+                            );
+                            let response = format!(
+                                "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{}",
+                                body.len(),
+                                body
+                            );
+                            if socket.write_all(response.as_bytes()).await.is_err() {
+                                return;
+                            }
+                        }
+                    }
+                });
+            }
+        });
+        url
+    }
+
+    fn make_local_client(base_url: String) -> HnClient {
+        HnClient::init(HnClientConfig {
+            base_url,
+            requests_per_second: 0,
+            max_concurrency: PARALLEL_REQUESTS,
+            ..HnClientConfig::default()
+        })
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn items_share_pooled_connections() {
+        let connections = Arc::new(AtomicUsize::new(0));
+        let client = make_local_client(serve_items(connections.clone()).await);
+        let ids: Vec<u32> = (1..=50).collect();
+        let result = client.get_submissions(&ids).await;
+        assert_eq!(result.items.len(), 50);
+        // At most one connection for each request in flight
+        assert!(connections.load(Ordering::SeqCst) <= PARALLEL_REQUESTS);
+    }
+
+    // Compares a batch of 500 items through the pooled client against a new client
+    // per request, i.e. `reqwest::get`
+    // Run with `cargo test --release bench -- --ignored --nocapture`
+    #[tokio::test]
+    #[ignore]
+    async fn bench_pooled_client_throughput() {
+        let ids: Vec<u32> = (1..=500).collect();
+
+        let pooled_connections = Arc::new(AtomicUsize::new(0));
+        let client = make_local_client(serve_items(pooled_connections.clone()).await);
+        let start = Instant::now();
+        let result = client.get_submissions(&ids).await;
+        let pooled_elapsed = start.elapsed();
+        assert_eq!(result.items.len(), ids.len());
+
+        let unpooled_connections = Arc::new(AtomicUsize::new(0));
+        let base_url = serve_items(unpooled_connections.clone()).await;
+        let start = Instant::now();
+        let responses: Vec<bool> = stream::iter(ids.iter().cloned())
+            .map(|id| {
+                let route = format!("{}/item/{}.json", base_url, id);
+                async move {
+                    match reqwest::get(&route).await {
+                        Ok(response) => response.json::<Item>().await.is_ok(),
+                        Err(_) => false,
+                    }
+                }
+            })
+            .buffered(PARALLEL_REQUESTS)
+            .collect()
+            .await;
+        let unpooled_elapsed = start.elapsed();
+        assert!(responses.iter().all(|ok| *ok));
+
+        for (name, elapsed, connections) in [
+            ("pooled", pooled_elapsed, &pooled_connections),
+            ("per request", unpooled_elapsed, &unpooled_connections),
+        ]
+        .iter()
+        {
+            println!(
+                "{}: {} items in {:?} ({:.0} items/s) over {} connections",
+                name,
+                ids.len(),
+                elapsed,
+                ids.len() as f64 / elapsed.as_secs_f64(),
+                connections.load(Ordering::SeqCst)
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn requests_and_batches_time_out() {
+        let base_url = serve_nothing().await;
+        let client = HnClient::init(HnClientConfig {
+            base_url: base_url.clone(),
+            timeout: Duration::from_millis(50),
+            max_retries: 0,
+            ..HnClientConfig::default()
+        })
+        .unwrap();
+        let post_ids = client.get_post_ids(&StoryListType::Top).await;
+        assert!(matches!(post_ids, Err(HnError::Timeout(_))));
+
+        // Requests are retried until the batch runs out of time
+        let client = HnClient::init(HnClientConfig {
+            base_url,
+            timeout: Duration::from_millis(50),
+            batch_timeout: Duration::from_millis(200),
+            max_retries: 100,
+            retry_base_delay: Duration::from_millis(1),
+            ..HnClientConfig::default()
+        })
+        .unwrap();
+        let start = Instant::now();
+        let result = get_stories(&client, &[1, 2, 3]).await;
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert!(result.items.is_empty());
+        assert_eq!(result.failed.len(), 3);
+        for (_, error) in result.failed {
+            assert!(
+                matches!(error, HnError::Timeout(duration) if duration == Duration::from_millis(200))
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn cancelled_fetches_stop() {
+        let client = HnClient::init(HnClientConfig {
+            base_url: serve_nothing().await,
+            ..HnClientConfig::default()
+        })
+        .unwrap();
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let fetch_handle = spawn_fetch(async move {
+            let stories = get_stories(&client, &[1]).await;
+            let _ = sender.send(stories.items.len());
+        });
+        fetch_handle.cancel();
+        // The sender is dropped with the fetch without sending anything
+        assert_eq!(receiver.recv().await, None);
+    }
 }
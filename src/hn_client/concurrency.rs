@@ -0,0 +1,184 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+
+// Requests slower than this multiple of the average latency count as congestion
+const LATENCY_TOLERANCE: f64 = 2.0;
+// Weight of each new latency in the average
+const LATENCY_SMOOTHING: f64 = 0.2;
+const BACKOFF_FACTOR: f64 = 0.5;
+
+// How a request went, for adjusting the limit
+pub enum Outcome {
+    Success(Duration),
+    // The API is overloaded or throttling us, e.g. timeouts, 429s and 5xxs
+    Overloaded,
+    // Errors that say nothing about load, e.g. a 404
+    Ignored,
+}
+
+struct LimitState {
+    limit: f64,
+    in_flight: usize,
+    average_latency: Option<Duration>,
+    // Requests started before the last decrease do not decrease the limit again,
+    // so a burst of failures only backs off once
+    last_decrease: Option<Instant>,
+}
+
+// Limits the number of requests in flight, adjusted AIMD style: the limit grows by
+// one each time a full window of requests succeeds, and is halved when requests fail
+// from overload or take much longer than usual
+pub struct AdaptiveLimit {
+    min: usize,
+    max: usize,
+    state: Mutex<LimitState>,
+    released: Notify,
+}
+
+// A slot for one request in flight, freed when dropped
+pub struct Permit<'a> {
+    limit: &'a AdaptiveLimit,
+    started: Instant,
+}
+
+impl AdaptiveLimit {
+    pub fn init(initial: usize, min: usize, max: usize) -> AdaptiveLimit {
+        let min = min.max(1);
+        let max = max.max(min);
+        AdaptiveLimit {
+            min,
+            max,
+            state: Mutex::new(LimitState {
+                limit: initial.max(min).min(max) as f64,
+                in_flight: 0,
+                average_latency: None,
+                last_decrease: None,
+            }),
+            released: Notify::new(),
+        }
+    }
+
+    // The current number of requests allowed in flight
+    pub fn current(&self) -> usize {
+        self.state.lock().unwrap().limit as usize
+    }
+
+    // Waits until there is room for another request
+    pub async fn acquire(&self) -> Permit<'_> {
+        loop {
+            {
+                let mut state = self.state.lock().unwrap();
+                if state.in_flight < state.limit as usize {
+                    state.in_flight += 1;
+                    // Pass the wake up on in case a release was missed while waiting
+                    if state.in_flight < state.limit as usize {
+                        self.released.notify();
+                    }
+                    return Permit {
+                        limit: self,
+                        started: Instant::now(),
+                    };
+                }
+            }
+            self.released.notified().await;
+        }
+    }
+
+    fn record(&self, started: Instant, outcome: Outcome) {
+        let mut state = self.state.lock().unwrap();
+        let congested = match outcome {
+            Outcome::Success(latency) => {
+                let average = state.average_latency.unwrap_or(latency);
+                let congested = latency.as_secs_f64() > average.as_secs_f64() * LATENCY_TOLERANCE;
+                state.average_latency = Some(
+                    average.mul_f64(1.0 - LATENCY_SMOOTHING) + latency.mul_f64(LATENCY_SMOOTHING),
+                );
+                congested
+            }
+            Outcome::Overloaded => true,
+            Outcome::Ignored => return,
+        };
+        if !congested {
+            state.limit = (state.limit + 1.0 / state.limit).min(self.max as f64);
+        } else if state
+            .last_decrease
+            .is_none_or(|decrease| started > decrease)
+        {
+            state.limit = (state.limit * BACKOFF_FACTOR).max(self.min as f64);
+            state.last_decrease = Some(Instant::now());
+        }
+    }
+}
+
+impl<'a> Permit<'a> {
+    pub fn finish(self, outcome: Outcome) {
+        self.limit.record(self.started, outcome);
+    }
+}
+
+impl<'a> Drop for Permit<'a> {
+    fn drop(&mut self) {
+        self.limit.state.lock().unwrap().in_flight -= 1;
+        self.limit.released.notify();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::FutureExt;
+
+    async fn succeed(limit: &AdaptiveLimit, times: usize) {
+        for _ in 0..times {
+            let permit = limit.acquire().await;
+            permit.finish(Outcome::Success(Duration::from_millis(10)));
+        }
+    }
+
+    #[tokio::test]
+    async fn limit_grows_and_backs_off() {
+        let limit = AdaptiveLimit::init(2, 1, 4);
+        // Each success adds 1 / limit, so roughly one window of requests adds one
+        succeed(&limit, 3).await;
+        assert_eq!(limit.current(), 3);
+        succeed(&limit, 20).await;
+        assert_eq!(limit.current(), 4);
+
+        // Concurrent failures only back off once
+        let first = limit.acquire().await;
+        let second = limit.acquire().await;
+        first.finish(Outcome::Overloaded);
+        second.finish(Outcome::Overloaded);
+        assert_eq!(limit.current(), 2);
+
+        limit.acquire().await.finish(Outcome::Overloaded);
+        limit.acquire().await.finish(Outcome::Overloaded);
+        assert_eq!(limit.current(), 1);
+        // Errors unrelated to load leave the limit alone
+        limit.acquire().await.finish(Outcome::Ignored);
+        assert_eq!(limit.current(), 1);
+    }
+
+    #[tokio::test]
+    async fn slow_requests_back_off() {
+        let limit = AdaptiveLimit::init(4, 1, 8);
+        succeed(&limit, 5).await;
+        assert_eq!(limit.current(), 5);
+        limit
+            .acquire()
+            .await
+            .finish(Outcome::Success(Duration::from_millis(100)));
+        assert_eq!(limit.current(), 2);
+    }
+
+    #[tokio::test]
+    async fn acquire_waits_for_a_release() {
+        let limit = AdaptiveLimit::init(1, 1, 1);
+        let permit = limit.acquire().await;
+        let mut waiting = Box::pin(limit.acquire());
+        assert!((&mut waiting).now_or_never().is_none());
+        drop(permit);
+        assert!(waiting.now_or_never().is_some());
+    }
+}
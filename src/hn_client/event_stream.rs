@@ -0,0 +1,347 @@
+use super::sse::{SseEvent, SseParser};
+use super::HnError;
+use futures::{stream, Stream};
+use reqwest::header::ACCEPT;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::collections::VecDeque;
+use std::time::Duration;
+use tokio::time::{delay_for, timeout};
+
+// Firebase sends a keep-alive event every 30 seconds, a connection that is
+// quiet for longer than this is assumed to be dead
+const IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+// Reconnect delays double up to 2^5 times the base delay
+const MAX_BACKOFF_EXPONENT: u32 = 5;
+
+// Body of put and patch events
+#[derive(Debug, Deserialize)]
+struct Change {
+    path: String,
+    data: Value,
+}
+
+// Events sent by Firebase streaming endpoints, see
+// https://firebase.google.com/docs/reference/rest/database#section-streaming
+enum FirebaseEvent {
+    Put(Change),
+    Patch(Change),
+    KeepAlive,
+    // The location can no longer be read, the stream is not reopened
+    Cancel,
+    AuthRevoked,
+}
+
+// Unknown events are skipped
+fn parse_event(event: &SseEvent) -> Result<Option<FirebaseEvent>, HnError> {
+    Ok(match event.event.as_str() {
+        "put" => Some(FirebaseEvent::Put(serde_json::from_str(&event.data)?)),
+        "patch" => Some(FirebaseEvent::Patch(serde_json::from_str(&event.data)?)),
+        "keep-alive" => Some(FirebaseEvent::KeepAlive),
+        "cancel" => Some(FirebaseEvent::Cancel),
+        "auth_revoked" => Some(FirebaseEvent::AuthRevoked),
+        _ => None,
+    })
+}
+
+// Firebase arrays are objects with numeric keys, so they are indexed into directly
+// Anything other than an array or object is replaced with an object
+fn child_mut<'a>(value: &'a mut Value, key: &str) -> &'a mut Value {
+    let index = key.parse::<usize>().ok().filter(|_| value.is_array());
+    if index.is_none() && !value.is_object() {
+        *value = Value::Object(Map::new());
+    }
+    match (value, index) {
+        (Value::Array(array), Some(index)) => {
+            if index >= array.len() {
+                array.resize(index + 1, Value::Null);
+            }
+            &mut array[index]
+        }
+        (Value::Object(map), _) => map.entry(key).or_insert(Value::Null),
+        _ => unreachable!("value is an array or an object"),
+    }
+}
+
+// Replaces the value at `path`
+fn put(document: &mut Value, path: &str, data: Value) {
+    let mut target = document;
+    for key in path.split('/').filter(|key| !key.is_empty()) {
+        target = child_mut(target, key);
+    }
+    *target = data;
+}
+
+// Replaces each child of `path` that is in `data`
+fn patch(document: &mut Value, path: &str, data: Value) {
+    match data {
+        Value::Object(children) => {
+            for (key, child) in children {
+                put(document, &format!("{}/{}", path, key), child);
+            }
+        }
+        data => put(document, path, data),
+    }
+}
+
+// A streamed location, the connection is reopened whenever it drops
+struct Watch<T, F> {
+    client: reqwest::Client,
+    route: String,
+    reconnect_delay: Duration,
+    decode: F,
+    response: Option<reqwest::Response>,
+    parser: SseParser,
+    // The value at the location, built up from put and patch events
+    document: Value,
+    // Values and errors that have not been returned from the stream yet
+    pending: VecDeque<Result<T, HnError>>,
+    // Connection attempts since data was last received
+    failures: u32,
+    closed: bool,
+}
+
+impl<T, F> Watch<T, F>
+where
+    F: Fn(&Value) -> Result<T, HnError>,
+{
+    async fn connect(&mut self) {
+        if self.failures > 0 {
+            let exponent = (self.failures - 1).min(MAX_BACKOFF_EXPONENT);
+            delay_for(self.reconnect_delay * 2u32.pow(exponent)).await;
+        }
+        let response = self
+            .client
+            .get(&self.route)
+            .header(ACCEPT, "text/event-stream")
+            .send()
+            .await;
+        match response {
+            Ok(response) if response.status().is_success() => {
+                // Each connection starts with a put of the whole location
+                self.parser = SseParser::init();
+                self.response = Some(response);
+            }
+            Ok(response) => {
+                self.failures += 1;
+                self.pending
+                    .push_back(Err(HnError::Status(response.status())));
+            }
+            Err(error) => {
+                self.failures += 1;
+                self.pending.push_back(Err(HnError::Network(error)));
+            }
+        }
+    }
+
+    async fn read(&mut self, mut response: reqwest::Response) {
+        match timeout(IDLE_TIMEOUT, response.chunk()).await {
+            Ok(Ok(Some(chunk))) => {
+                for event in self.parser.push(&chunk) {
+                    self.handle(event);
+                }
+                if !self.closed {
+                    self.response = Some(response);
+                }
+            }
+            Ok(Err(error)) => {
+                self.failures += 1;
+                self.pending.push_back(Err(HnError::Network(error)));
+            }
+            // The connection was closed or went quiet, it is reopened
+            Ok(Ok(None)) | Err(_) => self.failures += 1,
+        }
+    }
+
+    fn handle(&mut self, event: SseEvent) {
+        match parse_event(&event) {
+            Ok(Some(FirebaseEvent::Put(change))) => {
+                put(&mut self.document, &change.path, change.data);
+                self.failures = 0;
+                self.pending.push_back((self.decode)(&self.document));
+            }
+            Ok(Some(FirebaseEvent::Patch(change))) => {
+                patch(&mut self.document, &change.path, change.data);
+                self.failures = 0;
+                self.pending.push_back((self.decode)(&self.document));
+            }
+            Ok(Some(FirebaseEvent::KeepAlive)) | Ok(None) => {}
+            Ok(Some(FirebaseEvent::Cancel)) | Ok(Some(FirebaseEvent::AuthRevoked)) => {
+                self.closed = true;
+                self.pending
+                    .push_back(Err(HnError::StreamClosed(event.event)));
+            }
+            Err(error) => self.pending.push_back(Err(error)),
+        }
+    }
+}
+
+// Streams the value at `route` each time it changes, decoded with `decode`
+// Connection and decode errors are returned from the stream as they happen,
+// the stream only ends if the server cancels it
+pub fn watch<T, F>(
+    client: reqwest::Client,
+    route: String,
+    reconnect_delay: Duration,
+    decode: F,
+) -> impl Stream<Item = Result<T, HnError>>
+where
+    F: Fn(&Value) -> Result<T, HnError>,
+{
+    let watch = Watch {
+        client,
+        route,
+        reconnect_delay,
+        decode,
+        response: None,
+        parser: SseParser::init(),
+        document: Value::Null,
+        pending: VecDeque::new(),
+        failures: 0,
+        closed: false,
+    };
+    stream::unfold(watch, |mut watch| async move {
+        loop {
+            if let Some(change) = watch.pending.pop_front() {
+                return Some((change, watch));
+            }
+            if watch.closed {
+                return None;
+            }
+            match watch.response.take() {
+                Some(response) => watch.read(response).await,
+                None => watch.connect().await,
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hn_client::{HnClient, HnClientConfig};
+    use crate::stores::data::{Story, Submission};
+    use crate::stores::view::StoryListType;
+    use futures::StreamExt;
+    use serde_json::json;
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    // Stand-in for a Firebase streaming endpoint - each connection is sent the
+    // next list of chunks as an event stream and then closed
+    // Returns the server url and the headers of each request received
+    async fn serve_event_streams(
+        connections: Vec<Vec<&'static str>>,
+    ) -> (String, Arc<Mutex<Vec<String>>>) {
+        let mut listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let received = requests.clone();
+        tokio::spawn(async move {
+            for chunks in connections {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                let mut buffer = [0; 1024];
+                while !request.ends_with(b"\r\n\r\n") {
+                    let read = socket.read(&mut buffer).await.unwrap();
+                    if read == 0 {
+                        break;
+                    }
+                    request.extend_from_slice(&buffer[..read]);
+                }
+                received
+                    .lock()
+                    .unwrap()
+                    .push(String::from_utf8_lossy(&request).to_lowercase());
+
+                socket
+                    .write_all(b"HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\nconnection: close\r\n\r\n")
+                    .await
+                    .unwrap();
+                for chunk in chunks {
+                    socket.write_all(chunk.as_bytes()).await.unwrap();
+                    socket.flush().await.unwrap();
+                    delay_for(Duration::from_millis(10)).await;
+                }
+            }
+        });
+        (url, requests)
+    }
+
+    fn make_client(base_url: String) -> HnClient {
+        HnClient::init(HnClientConfig {
+            base_url,
+            stream_reconnect_delay: Duration::from_millis(10),
+            ..HnClientConfig::default()
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn put_and_patch_update_the_document() {
+        let mut document = Value::Null;
+        put(&mut document, "/", json!({"id": 1, "kids": [2, 3]}));
+        patch(&mut document, "/", json!({"score": 10}));
+        put(&mut document, "/kids/2", json!(4));
+        patch(&mut document, "/kids", json!({"0": 5}));
+        assert_eq!(document, json!({"id": 1, "kids": [5, 3, 4], "score": 10}));
+
+        put(&mut document, "/", json!([1]));
+        assert_eq!(document, json!([1]));
+    }
+
+    #[tokio::test]
+    async fn story_list_is_streamed_across_reconnects() {
+        let (url, requests) = serve_event_streams(vec![
+            vec![
+                "event: put\ndata: {\"path\":\"/\",\"data\":[1,2,3]}\n\n",
+                "event: keep-alive\ndata: null\n\nevent: patch\ndata: {\"path\":\"/\",",
+                "\"data\":{\"1\":5}}\n\n",
+            ],
+            // The connection is reopened after the server closes it
+            vec!["event: put\ndata: {\"path\":\"/\",\"data\":[7,8]}\n\n"],
+        ])
+        .await;
+
+        let post_ids: Vec<Vec<u32>> = make_client(url)
+            .watch_post_ids(&StoryListType::Top)
+            .take(3)
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        assert_eq!(post_ids, vec![vec![1, 2, 3], vec![1, 5, 3], vec![7, 8]]);
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[0].starts_with("get /topstories.json"));
+        assert!(requests[0].contains("accept: text/event-stream"));
+    }
+
+    #[tokio::test]
+    async fn item_is_streamed_until_cancelled() {
+        let (url, requests) = serve_event_streams(vec![vec![
+            "event: put\ndata: {\"path\":\"/\",\"data\":{\"by\":\"pg\",\"descendants\":0,\"id\":1,\"score\":1,\"time\":0,\"title\":\"title\",\"type\":\"story\"}}\n\n",
+            "event: patch\ndata: {\"path\":\"/\",\"data\":{\"descendants\":1,\"kids\":[2],\"score\":5}}\n\n",
+            "event: cancel\ndata: null\n\n",
+        ]])
+        .await;
+
+        let changes: Vec<Result<Submission, HnError>> =
+            make_client(url).watch_item(1).collect().await;
+        assert_eq!(changes.len(), 3);
+        let scores: Vec<(u16, Vec<u32>)> = changes[..2]
+            .iter()
+            .map(|change| match change {
+                Ok(Submission::Story(Story::Post(post))) => (post.score, post.children.clone()),
+                _ => panic!("Unexpected change"),
+            })
+            .collect();
+        assert_eq!(scores, vec![(1, vec![]), (5, vec![2])]);
+        assert!(matches!(changes[2], Err(HnError::StreamClosed(_))));
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 1);
+        assert!(requests[0].starts_with("get /item/1.json"));
+    }
+}
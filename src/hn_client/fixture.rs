@@ -0,0 +1,235 @@
+// Serves HN data from JSON files laid out like the HN API, e.g. saved with
+// `curl https://hacker-news.firebaseio.com/v0/item/1.json > fixtures/item/1.json`:
+//
+// fixtures/topstories.json (and the other story lists)
+// fixtures/item/{id}.json
+// fixtures/user/{id}.json
+//
+// Everything is read into memory up front so the app can run without a network
+use super::{
+    crawl_comment_tree, to_any_item, to_batch, to_comment_result, to_poll_option, to_submission,
+    BatchResult, CommentTree, CommentTreeLimits, HnError, HnUser, Item,
+};
+use crate::backend::HnBackend;
+use crate::stores::data::{AnyItem, Comment, PollOption, Submission, User};
+use crate::stores::view::StoryListType;
+use futures::future::{self, BoxFuture, FutureExt};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+pub struct FixtureBackend {
+    post_ids: HashMap<StoryListType, Vec<u32>>,
+    // Parsed when requested, so one malformed item only fails that item
+    items: HashMap<u32, Value>,
+    users: HashMap<String, Value>,
+}
+
+fn list_file_name(story_type: &StoryListType) -> &'static str {
+    match story_type {
+        StoryListType::Top => "topstories.json",
+        StoryListType::Best => "beststories.json",
+        StoryListType::New => "newstories.json",
+        StoryListType::Ask => "askstories.json",
+        StoryListType::Show => "showstories.json",
+        StoryListType::Job => "jobstories.json",
+    }
+}
+
+// The JSON files in `dir` by file stem, a missing directory has no files
+fn read_json_files(dir: &Path) -> Result<Vec<(String, Value)>, HnError> {
+    let mut files = Vec::new();
+    if !dir.is_dir() {
+        return Ok(files);
+    }
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|extension| extension.to_str()) != Some("json") {
+            continue;
+        }
+        if let Some(stem) = path.file_stem().and_then(|stem| stem.to_str()) {
+            let value = serde_json::from_slice(&fs::read(&path)?)?;
+            files.push((stem.to_string(), value));
+        }
+    }
+    Ok(files)
+}
+
+impl FixtureBackend {
+    pub fn load(dir: &Path) -> Result<FixtureBackend, HnError> {
+        let mut post_ids = HashMap::new();
+        for story_type in StoryListType::ALL.iter() {
+            let path = dir.join(list_file_name(story_type));
+            // Lists without a file are empty
+            if path.is_file() {
+                let ids: Vec<u32> = serde_json::from_slice(&fs::read(path)?)?;
+                post_ids.insert(*story_type, ids);
+            }
+        }
+        let mut items = HashMap::new();
+        for (stem, value) in read_json_files(&dir.join("item"))? {
+            if let Ok(id) = stem.parse() {
+                items.insert(id, value);
+            }
+        }
+        let users = read_json_files(&dir.join("user"))?.into_iter().collect();
+        Ok(FixtureBackend {
+            post_ids,
+            items,
+            users,
+        })
+    }
+
+    fn read_item(&self, id: u32) -> Result<Item, HnError> {
+        match self.items.get(&id) {
+            Some(value) => Ok(serde_json::from_value(value.clone())?),
+            None => Err(HnError::NullItem(id)),
+        }
+    }
+
+    fn get_items(&self, ids: &[u32]) -> Vec<(u32, Result<Item, HnError>)> {
+        ids.iter().map(|id| (*id, self.read_item(*id))).collect()
+    }
+
+    fn get_comments(&self, ids: &[u32]) -> BatchResult<Comment> {
+        to_batch(self.get_items(ids), to_comment_result)
+    }
+}
+
+impl HnBackend for FixtureBackend {
+    fn get_post_ids<'a>(
+        &'a self,
+        story_type: &'a StoryListType,
+    ) -> BoxFuture<'a, Result<Vec<u32>, HnError>> {
+        let post_ids = self.post_ids.get(story_type).cloned().unwrap_or_default();
+        future::ready(Ok(post_ids)).boxed()
+    }
+
+    fn get_submissions<'a>(&'a self, ids: &'a [u32]) -> BoxFuture<'a, BatchResult<Submission>> {
+        future::ready(to_batch(self.get_items(ids), to_submission)).boxed()
+    }
+
+    fn get_poll_options<'a>(&'a self, ids: &'a [u32]) -> BoxFuture<'a, BatchResult<PollOption>> {
+        future::ready(to_batch(self.get_items(ids), to_poll_option)).boxed()
+    }
+
+    fn get_item(&self, id: u32) -> BoxFuture<'_, Result<AnyItem, HnError>> {
+        future::ready(to_any_item(id, self.read_item(id))).boxed()
+    }
+
+    fn get_user<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Result<User, HnError>> {
+        let user = match self.users.get(id) {
+            Some(value) => serde_json::from_value::<HnUser>(value.clone())
+                .map(|user| User {
+                    id: user.id,
+                    created: user.created,
+                    karma: user.karma,
+                    about: user.about,
+                    submitted: user.submitted,
+                })
+                .map_err(HnError::from),
+            None => Err(HnError::NullUser(id.to_string())),
+        };
+        future::ready(user).boxed()
+    }
+
+    fn get_comment_tree<'a>(
+        &'a self,
+        parent: u32,
+        children: &'a [u32],
+        limits: &'a CommentTreeLimits,
+    ) -> BoxFuture<'a, CommentTree> {
+        crawl_comment_tree(parent, children, limits, move |ids| {
+            future::ready(self.get_comments(&ids))
+        })
+        .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stores::data::Story;
+    use futures::StreamExt;
+    use std::path::PathBuf;
+
+    fn load_fixtures() -> FixtureBackend {
+        let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("fixtures");
+        FixtureBackend::load(&dir).unwrap()
+    }
+
+    #[tokio::test]
+    async fn fixtures_are_served_like_the_api() {
+        let backend = load_fixtures();
+        let post_ids = backend.get_post_ids(&StoryListType::Top).await.unwrap();
+        assert_eq!(post_ids, vec![1, 2, 3]);
+        // Lists without a file are empty
+        assert!(backend
+            .get_post_ids(&StoryListType::Show)
+            .await
+            .unwrap()
+            .is_empty());
+
+        let stories: Vec<(usize, Result<Story, HnError>)> =
+            backend.stream_stories(&[3, 1, 404]).collect().await;
+        assert!(matches!(stories[0], (0, Ok(Story::Poll(_)))));
+        assert!(matches!(stories[1], (1, Ok(Story::Post(_)))));
+        assert!(matches!(stories[2], (2, Err(HnError::NullItem(404)))));
+
+        let options = backend.get_poll_options(&[6, 7]).await;
+        assert_eq!(options.items.len(), 2);
+        assert!(matches!(
+            backend.get_item(6).await,
+            Ok(AnyItem::PollOption(PollOption { poll: 3, .. }))
+        ));
+        assert!(matches!(backend.get_item(4).await, Ok(AnyItem::Comment(_))));
+
+        let user = backend.get_user("pg").await.unwrap();
+        assert_eq!(user.submitted, vec![1, 3]);
+        assert!(matches!(
+            backend.get_user("nobody").await,
+            Err(HnError::NullUser(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn ancestors_are_loaded_up_to_the_story() {
+        let backend = load_fixtures();
+        let ancestors = backend.get_ancestors(8).await;
+        let ids: Vec<u32> = ancestors
+            .items
+            .iter()
+            .map(|item| match item {
+                AnyItem::Comment(comment) => comment.id,
+                AnyItem::Post(post) => post.id,
+                _ => panic!("Unexpected AnyItem variant"),
+            })
+            .collect();
+        assert_eq!(ids, vec![8, 4, 1]);
+        assert!(ancestors.failed.is_empty());
+
+        let ancestors = backend.get_ancestors(404).await;
+        assert!(ancestors.items.is_empty());
+        assert!(matches!(
+            ancestors.failed[..],
+            [(404, HnError::NullItem(404))]
+        ));
+    }
+
+    #[tokio::test]
+    async fn fixture_comment_tree_respects_limits() {
+        let backend = load_fixtures();
+        let limits = CommentTreeLimits {
+            max_depth: 1,
+            max_children: 10,
+            max_items: 10,
+        };
+        let tree = backend.get_comment_tree(1, &[4, 5], &limits).await;
+        let ids: Vec<u32> = tree.comments.iter().map(|comment| comment.id).collect();
+        assert_eq!(ids, vec![4, 5]);
+        // The reply to 4 is past the depth limit
+        assert_eq!(tree.more_replies.len(), 1);
+        assert_eq!(tree.more_replies[0].parent, 4);
+    }
+}
@@ -0,0 +1,63 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::time::delay_until;
+
+// Spaces requests evenly so no more than `requests_per_second` are sent
+// Shared by every request the client makes, including concurrent batches
+pub struct RateLimiter {
+    interval: Option<Duration>,
+    // The earliest time the next request can be sent
+    next_slot: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    // 0 requests per second disables the limit
+    pub fn init(requests_per_second: u32) -> RateLimiter {
+        RateLimiter {
+            interval: if requests_per_second == 0 {
+                None
+            } else {
+                Some(Duration::from_secs(1) / requests_per_second)
+            },
+            next_slot: Mutex::new(Instant::now()),
+        }
+    }
+
+    // Waits until a request can be sent
+    pub async fn acquire(&self) {
+        let interval = match self.interval {
+            Some(interval) => interval,
+            None => return,
+        };
+        let slot = {
+            let mut next_slot = self.next_slot.lock().unwrap();
+            let slot = (*next_slot).max(Instant::now());
+            *next_slot = slot + interval;
+            slot
+        };
+        delay_until(slot.into()).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::future::join_all;
+
+    #[tokio::test]
+    async fn requests_are_spaced_by_the_rate() {
+        let rate_limiter = RateLimiter::init(50);
+        let start = Instant::now();
+        join_all((0..6).map(|_| rate_limiter.acquire())).await;
+        // The first request is sent straight away, the other 5 are 20ms apart
+        assert!(start.elapsed() >= Duration::from_millis(100));
+    }
+
+    #[tokio::test]
+    async fn zero_disables_the_limit() {
+        let rate_limiter = RateLimiter::init(0);
+        let start = Instant::now();
+        join_all((0..100).map(|_| rate_limiter.acquire())).await;
+        assert!(start.elapsed() < Duration::from_millis(100));
+    }
+}
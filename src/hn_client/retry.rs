@@ -0,0 +1,50 @@
+use rand::Rng;
+use std::time::Duration;
+
+// Exponential backoff with full jitter, see
+// https://aws.amazon.com/blogs/architecture/exponential-backoff-and-jitter/
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+    // The longest delay before retry number `retry` (starting from 1)
+    fn max_delay_for(&self, retry: u32) -> Duration {
+        let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+        self.base_delay
+            .checked_mul(factor)
+            .map_or(self.max_delay, |delay| delay.min(self.max_delay))
+    }
+
+    // A random delay up to the backoff for retry number `retry`, so that
+    // requests that failed together are not retried together
+    pub fn delay(&self, retry: u32) -> Duration {
+        let max_delay = self.max_delay_for(retry);
+        let millis = rand::thread_rng().gen_range(0, max_delay.as_millis() as u64 + 1);
+        Duration::from_millis(millis)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delays_back_off_up_to_the_max() {
+        let policy = RetryPolicy {
+            max_retries: 10,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(1000),
+        };
+        assert_eq!(policy.max_delay_for(1), Duration::from_millis(100));
+        assert_eq!(policy.max_delay_for(2), Duration::from_millis(200));
+        assert_eq!(policy.max_delay_for(4), Duration::from_millis(800));
+        assert_eq!(policy.max_delay_for(5), Duration::from_millis(1000));
+        assert_eq!(policy.max_delay_for(40), Duration::from_millis(1000));
+        for retry in 1..10 {
+            assert!(policy.delay(retry) <= policy.max_delay_for(retry));
+        }
+    }
+}
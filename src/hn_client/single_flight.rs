@@ -0,0 +1,140 @@
+use futures::channel::oneshot;
+use futures::Future;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+// Coalesces concurrent calls for the same key, e.g. a background prefetch and a page
+// load both fetching an item, so that only one of them does the work
+pub struct SingleFlight<T> {
+    // The callers waiting on each call in flight
+    waiting: Mutex<HashMap<String, Vec<oneshot::Sender<T>>>>,
+}
+
+// Clears the call from `waiting` when the caller running it is dropped, e.g. cancelled
+// or timed out, so that its waiters fall back to running it themselves
+struct Leader<'a, T> {
+    flight: &'a SingleFlight<T>,
+    key: &'a str,
+    done: bool,
+}
+
+impl<T: Clone> SingleFlight<T> {
+    pub fn init() -> SingleFlight<T> {
+        SingleFlight {
+            waiting: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // Runs `call` unless a call for `key` is already in flight, in which case that
+    // call's result is returned instead
+    pub async fn run<F, Fut>(&self, key: &str, call: F) -> T
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = T>,
+    {
+        loop {
+            let receiver = {
+                let mut waiting = self.waiting.lock().unwrap();
+                match waiting.get_mut(key) {
+                    Some(waiters) => {
+                        let (sender, receiver) = oneshot::channel();
+                        waiters.push(sender);
+                        receiver
+                    }
+                    None => {
+                        waiting.insert(key.to_string(), Vec::new());
+                        break;
+                    }
+                }
+            };
+            // The call was dropped before it finished, so try again
+            if let Ok(value) = receiver.await {
+                return value;
+            }
+        }
+
+        let mut leader = Leader {
+            flight: self,
+            key,
+            done: false,
+        };
+        let value = call().await;
+        for waiter in leader.finish() {
+            let _ = waiter.send(value.clone());
+        }
+        value
+    }
+
+    // The number of calls in flight
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.waiting.lock().unwrap().len()
+    }
+}
+
+impl<'a, T> Leader<'a, T> {
+    fn finish(&mut self) -> Vec<oneshot::Sender<T>> {
+        self.done = true;
+        self.flight
+            .waiting
+            .lock()
+            .unwrap()
+            .remove(self.key)
+            .unwrap_or_default()
+    }
+}
+
+impl<'a, T> Drop for Leader<'a, T> {
+    fn drop(&mut self) {
+        if !self.done {
+            self.flight.waiting.lock().unwrap().remove(self.key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::future::{self, join3, FutureExt};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+    use tokio::time::delay_for;
+
+    #[tokio::test]
+    async fn concurrent_calls_are_coalesced() {
+        let flight = SingleFlight::init();
+        let calls = AtomicUsize::new(0);
+        let call = || async {
+            let count = calls.fetch_add(1, Ordering::SeqCst) + 1;
+            // Stay in flight until the other calls have started
+            delay_for(Duration::from_millis(1)).await;
+            count
+        };
+        let (first, second, other) = join3(
+            flight.run("a", call),
+            flight.run("a", call),
+            flight.run("b", call),
+        )
+        .await;
+        // "a" ran once and both callers got its result, "b" ran separately
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert_eq!((first, second, other), (1, 1, 2));
+        assert_eq!(flight.len(), 0);
+
+        // Calls after the first has finished run again
+        assert_eq!(flight.run("a", call).await, 3);
+    }
+
+    #[tokio::test]
+    async fn waiters_run_the_call_when_the_first_is_dropped() {
+        let flight = SingleFlight::init();
+        let mut first = Box::pin(flight.run("a", future::pending::<u32>));
+        assert!((&mut first).now_or_never().is_none());
+        let mut second = Box::pin(flight.run("a", || future::ready(2)));
+        assert!((&mut second).now_or_never().is_none());
+
+        drop(first);
+        assert_eq!(second.await, 2);
+        assert_eq!(flight.len(), 0);
+    }
+}
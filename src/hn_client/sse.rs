@@ -0,0 +1,117 @@
+// Parser for the text/event-stream format, see
+// https://html.spec.whatwg.org/multipage/server-sent-events.html#event-stream-interpretation
+// `id` and `retry` fields are not used by Firebase and are ignored
+
+#[derive(Debug, PartialEq)]
+pub struct SseEvent {
+    pub event: String,
+    pub data: String,
+}
+
+// Bytes are buffered until a full line is received, so chunks can be split at any point
+pub struct SseParser {
+    buffer: Vec<u8>,
+    event: String,
+    data: Vec<String>,
+}
+
+impl SseParser {
+    pub fn init() -> SseParser {
+        SseParser {
+            buffer: Vec::new(),
+            event: String::new(),
+            data: Vec::new(),
+        }
+    }
+
+    // Returns the events completed by `chunk`
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.buffer.extend_from_slice(chunk);
+        let mut events = Vec::new();
+        while let Some(end) = self.buffer.iter().position(|byte| *byte == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches('\n').trim_end_matches('\r');
+            if let Some(event) = self.parse_line(line) {
+                events.push(event);
+            }
+        }
+        events
+    }
+
+    fn parse_line(&mut self, line: &str) -> Option<SseEvent> {
+        // A blank line ends the event, events without data are dropped
+        if line.is_empty() {
+            let event = std::mem::take(&mut self.event);
+            if self.data.is_empty() {
+                return None;
+            }
+            let data = std::mem::take(&mut self.data).join("\n");
+            return Some(SseEvent {
+                event: if event.is_empty() {
+                    String::from("message")
+                } else {
+                    event
+                },
+                data,
+            });
+        }
+        // Lines starting with a colon are comments
+        if line.starts_with(':') {
+            return None;
+        }
+
+        let (field, value) = match line.find(':') {
+            Some(index) => (&line[..index], &line[index + 1..]),
+            None => (line, ""),
+        };
+        let value = value.strip_prefix(' ').unwrap_or(value);
+        match field {
+            "event" => self.event = value.to_string(),
+            "data" => self.data.push(value.to_string()),
+            _ => {}
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_split_across_chunks() {
+        let mut parser = SseParser::init();
+        assert!(parser.push(b"event: put\nda").is_empty());
+        assert!(parser.push(b"ta: {\"path\":\"/\",").is_empty());
+        let events = parser.push(b"\"data\":1}\n\nevent: keep-alive\ndata: null\n\n");
+        assert_eq!(
+            events,
+            vec![
+                SseEvent {
+                    event: String::from("put"),
+                    data: String::from("{\"path\":\"/\",\"data\":1}"),
+                },
+                SseEvent {
+                    event: String::from("keep-alive"),
+                    data: String::from("null"),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn comments_multiline_data_and_crlf() {
+        let mut parser = SseParser::init();
+        let events =
+            parser.push(b": comment\r\ndata:first\r\ndata: second\r\n\r\nevent: empty\r\n\r\n");
+        // The event without data is dropped
+        assert_eq!(
+            events,
+            vec![SseEvent {
+                event: String::from("message"),
+                data: String::from("first\nsecond"),
+            }]
+        );
+    }
+}
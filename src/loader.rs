@@ -0,0 +1,406 @@
+use crate::algolia_client::AlgoliaClient;
+use crate::backend::HnBackend;
+use crate::hn_client::{
+    spawn_fetch, BatchResult, CommentTree, CommentTreeLimits, FetchHandle, HnError,
+};
+use crate::stores::data::{AnyItem, DataStore, PollOption, Story, Submission, User};
+use crate::stores::view::StoryListType;
+use crate::updates::{self, Changes};
+use futures::StreamExt;
+use std::sync::Arc;
+use tokio::sync::mpsc::UnboundedSender;
+
+// Data loaded in the background, sent to the main loop to be added to the store
+pub enum Loaded {
+    PostIds(StoryListType, Result<Vec<u32>, HnError>),
+    Story(u32, Result<Story, HnError>),
+    CommentTree(u32, CommentTree),
+    PollOptions(BatchResult<PollOption>),
+    User(String, Result<User, HnError>),
+    Submissions(BatchResult<Submission>),
+    // A story or comment that changed, e.g. pushed through an event stream
+    Item(Submission),
+    // An item of any kind opened by id
+    OpenedItem(u32, Result<AnyItem, HnError>),
+    // The posts and comments above a comment
+    Ancestors(BatchResult<AnyItem>),
+    // Changes to loaded data found by a check for updates
+    Updates(Result<Changes, HnError>),
+}
+
+fn describe_failures(failed: Vec<(u32, HnError)>) -> Vec<(u32, String)> {
+    failed
+        .into_iter()
+        .map(|(id, error)| (id, error.to_string()))
+        .collect()
+}
+
+// Adds loaded data to the store
+// Returns true for story lists, users, opened items and updates, as the page showing
+// them has more to load
+// Failures are recorded in the store to be shown in place of what failed
+pub fn apply_loaded(data_store: &mut DataStore, loaded: Loaded) -> bool {
+    match loaded {
+        Loaded::PostIds(story_type, Ok(post_ids)) => {
+            data_store.hydrate_post_ids(&story_type, post_ids);
+            return true;
+        }
+        Loaded::PostIds(story_type, Err(error)) => {
+            data_store.hydrate_failed_post_ids(&story_type, error.to_string());
+            return true;
+        }
+        Loaded::Story(_, Ok(story)) => data_store.hydrate_stories(vec![story]),
+        Loaded::Story(id, Err(error)) => {
+            data_store.hydrate_failed_items(vec![(id, error.to_string())])
+        }
+        Loaded::CommentTree(parent, tree) => {
+            data_store.hydrate_comments(tree.comments);
+            data_store.hydrate_failed_items(describe_failures(tree.failed));
+            data_store.hydrate_more_replies(parent, tree.more_replies);
+        }
+        Loaded::PollOptions(options) => {
+            data_store.hydrate_poll_options(options.items);
+            data_store.hydrate_failed_items(describe_failures(options.failed));
+        }
+        Loaded::User(_, Ok(user)) => {
+            data_store.hydrate_user(user);
+            return true;
+        }
+        Loaded::User(user_id, Err(error)) => {
+            data_store.hydrate_failed_user(&user_id, error.to_string())
+        }
+        Loaded::Submissions(submissions) => {
+            data_store.hydrate_submissions(submissions.items);
+            data_store.hydrate_failed_items(describe_failures(submissions.failed));
+        }
+        Loaded::Item(submission) => data_store.hydrate_submissions(vec![submission]),
+        Loaded::OpenedItem(_, Ok(item)) => {
+            data_store.hydrate_item(item);
+            return true;
+        }
+        Loaded::OpenedItem(id, Err(error)) => {
+            data_store.hydrate_failed_items(vec![(id, error.to_string())])
+        }
+        Loaded::Ancestors(ancestors) => {
+            for item in ancestors.items {
+                data_store.hydrate_item(item);
+            }
+            data_store.hydrate_failed_items(describe_failures(ancestors.failed));
+        }
+        Loaded::Updates(Ok(changes)) => {
+            updates::apply_changes(data_store, changes);
+            return true;
+        }
+        // Updates are best effort - the loaded data is kept if they fail
+        Loaded::Updates(Err(_)) => {}
+    };
+    false
+}
+
+pub fn load_post_ids(
+    backend: &Arc<dyn HnBackend>,
+    story_type: StoryListType,
+    sender: UnboundedSender<Loaded>,
+) -> FetchHandle {
+    let backend = backend.clone();
+    spawn_fetch(async move {
+        let post_ids = backend.get_post_ids(&story_type).await;
+        let _ = sender.send(Loaded::PostIds(story_type, post_ids));
+    })
+}
+
+// Stories are sent as each one loads so the list can be filled in as they arrive
+pub fn load_stories(
+    backend: &Arc<dyn HnBackend>,
+    post_ids: Vec<u32>,
+    sender: UnboundedSender<Loaded>,
+) -> FetchHandle {
+    let backend = backend.clone();
+    spawn_fetch(async move {
+        let mut stories = backend.stream_stories(&post_ids);
+        while let Some((index, story)) = stories.next().await {
+            let _ = sender.send(Loaded::Story(post_ids[index], story));
+        }
+    })
+}
+
+// With `algolia_client` the whole tree is loaded in one request, falling back to
+// loading `comment_ids` through `backend` if that fails
+pub fn load_comment_tree(
+    backend: &Arc<dyn HnBackend>,
+    algolia_client: Option<&Arc<AlgoliaClient>>,
+    parent: u32,
+    comment_ids: Vec<u32>,
+    limits: CommentTreeLimits,
+    sender: UnboundedSender<Loaded>,
+) -> FetchHandle {
+    let backend = backend.clone();
+    let algolia_client = algolia_client.cloned();
+    spawn_fetch(async move {
+        let full_tree = match &algolia_client {
+            Some(algolia_client) => algolia_client.get_full_comment_tree(parent).await.ok(),
+            None => None,
+        };
+        let tree = match full_tree {
+            Some(tree) => tree,
+            None => {
+                backend
+                    .get_comment_tree(parent, &comment_ids, &limits)
+                    .await
+            }
+        };
+        let _ = sender.send(Loaded::CommentTree(parent, tree));
+    })
+}
+
+pub fn load_poll_options(
+    backend: &Arc<dyn HnBackend>,
+    option_ids: Vec<u32>,
+    sender: UnboundedSender<Loaded>,
+) -> FetchHandle {
+    let backend = backend.clone();
+    spawn_fetch(async move {
+        let options = backend.get_poll_options(&option_ids).await;
+        let _ = sender.send(Loaded::PollOptions(options));
+    })
+}
+
+pub fn load_item(
+    backend: &Arc<dyn HnBackend>,
+    item_id: u32,
+    sender: UnboundedSender<Loaded>,
+) -> FetchHandle {
+    let backend = backend.clone();
+    spawn_fetch(async move {
+        let item = backend.get_item(item_id).await;
+        let _ = sender.send(Loaded::OpenedItem(item_id, item));
+    })
+}
+
+// Loads `item_id` and the items above it, for the context of a comment
+pub fn load_ancestors(
+    backend: &Arc<dyn HnBackend>,
+    item_id: u32,
+    sender: UnboundedSender<Loaded>,
+) -> FetchHandle {
+    let backend = backend.clone();
+    spawn_fetch(async move {
+        let ancestors = backend.get_ancestors(item_id).await;
+        let _ = sender.send(Loaded::Ancestors(ancestors));
+    })
+}
+
+pub fn load_user(
+    backend: &Arc<dyn HnBackend>,
+    user_id: String,
+    sender: UnboundedSender<Loaded>,
+) -> FetchHandle {
+    let backend = backend.clone();
+    spawn_fetch(async move {
+        let user = backend.get_user(&user_id).await;
+        let _ = sender.send(Loaded::User(user_id, user));
+    })
+}
+
+pub fn load_submissions(
+    backend: &Arc<dyn HnBackend>,
+    item_ids: Vec<u32>,
+    sender: UnboundedSender<Loaded>,
+) -> FetchHandle {
+    let backend = backend.clone();
+    spawn_fetch(async move {
+        let submissions = backend.get_submissions(&item_ids).await;
+        let _ = sender.send(Loaded::Submissions(submissions));
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algolia_client::AlgoliaClientConfig;
+    use crate::hn_client::{FixtureBackend, HnClient, HnClientConfig};
+    use mockito::mock;
+    use tokio::sync::mpsc;
+
+    fn make_client() -> Arc<dyn HnBackend> {
+        Arc::new(
+            HnClient::init(HnClientConfig {
+                base_url: mockito::server_url(),
+                max_retries: 0,
+                ..HnClientConfig::default()
+            })
+            .unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn loaded_stories_are_added_to_the_store() {
+        let story_mock = mock("GET", "/item/1.json")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{"by":"pg","descendants":0,"id":1,"score":1,"time":0,"title":"title","type":"story"}"#,
+            )
+            .expect(1)
+            .create();
+        let failed_mock = mock("GET", "/item/2.json")
+            .with_status(404)
+            .expect(1)
+            .create();
+
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        load_stories(&make_client(), vec![1, 2], sender);
+        let mut data_store = DataStore::init();
+        // The channel closes once the fetch completes
+        while let Some(loaded) = receiver.recv().await {
+            assert!(!apply_loaded(&mut data_store, loaded));
+        }
+        story_mock.assert();
+        failed_mock.assert();
+        assert!(data_store.get_post(&1).is_some());
+        assert!(data_store.get_failed_item(&2).is_some());
+    }
+
+    #[tokio::test]
+    async fn comment_tree_falls_back_to_hn_api() {
+        let algolia_mock = mock("GET", "/items/100")
+            .with_status(503)
+            .expect(1)
+            .create();
+        let comment_mock = mock("GET", "/item/101.json")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{"by":"pg","id":101,"parent":100,"text":"text","time":0,"type":"comment"}"#,
+            )
+            .expect(1)
+            .create();
+
+        let algolia_client = Arc::new(AlgoliaClient::init(
+            AlgoliaClientConfig {
+                base_url: mockito::server_url(),
+                ..AlgoliaClientConfig::default()
+            },
+            reqwest::Client::new(),
+        ));
+        let limits = CommentTreeLimits {
+            max_depth: 1,
+            max_children: 10,
+            max_items: 10,
+        };
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        load_comment_tree(
+            &make_client(),
+            Some(&algolia_client),
+            100,
+            vec![101],
+            limits,
+            sender,
+        );
+        let mut data_store = DataStore::init();
+        while let Some(loaded) = receiver.recv().await {
+            apply_loaded(&mut data_store, loaded);
+        }
+        algolia_mock.assert();
+        comment_mock.assert();
+        assert_eq!(data_store.get_comment(&101).unwrap().parent, 100);
+    }
+
+    #[tokio::test]
+    async fn fixture_list_and_stories_are_loaded() {
+        let dir = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("fixtures");
+        let backend: Arc<dyn HnBackend> = Arc::new(FixtureBackend::load(&dir).unwrap());
+        let mut data_store = DataStore::init();
+
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        load_post_ids(&backend, StoryListType::Top, sender);
+        let loaded = receiver.recv().await.unwrap();
+        assert!(apply_loaded(&mut data_store, loaded));
+
+        let post_ids = data_store.get_post_ids(&StoryListType::Top).to_vec();
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        load_stories(&backend, post_ids, sender);
+        while let Some(loaded) = receiver.recv().await {
+            apply_loaded(&mut data_store, loaded);
+        }
+        assert!(data_store.get_post(&1).is_some());
+        assert!(data_store.get_job(&2).is_some());
+        assert!(data_store.get_poll(&3).is_some());
+
+        // Opened items of any kind are added to the store
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        load_item(&backend, 6, sender);
+        let loaded = receiver.recv().await.unwrap();
+        assert!(apply_loaded(&mut data_store, loaded));
+        assert_eq!(data_store.get_poll_option(&6).unwrap().poll, 3);
+    }
+
+    #[tokio::test]
+    async fn comment_context_is_loaded() {
+        let dir = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("fixtures");
+        let backend: Arc<dyn HnBackend> = Arc::new(FixtureBackend::load(&dir).unwrap());
+        let mut data_store = DataStore::init();
+
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        load_item(&backend, 8, sender);
+        apply_loaded(&mut data_store, receiver.recv().await.unwrap());
+        let comment = data_store.get_comment(&8).unwrap();
+        let missing = data_store.get_ancestors(comment).1.unwrap();
+        assert_eq!(missing, 4);
+
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        load_ancestors(&backend, missing, sender);
+        apply_loaded(&mut data_store, receiver.recv().await.unwrap());
+        let comment = data_store.get_comment(&8).unwrap();
+        let (ancestors, missing) = data_store.get_ancestors(comment);
+        assert_eq!(ancestors.len(), 2);
+        assert_eq!(missing, None);
+    }
+
+    #[tokio::test]
+    async fn loaded_lists_and_users_have_more_to_load() {
+        let mut data_store = DataStore::init();
+        let loaded_list = Loaded::PostIds(StoryListType::Top, Ok(vec![1]));
+        assert!(apply_loaded(&mut data_store, loaded_list));
+        let loaded_user = Loaded::User(
+            String::from("pg"),
+            Ok(User {
+                id: String::from("pg"),
+                created: 0,
+                karma: 1,
+                about: None,
+                submitted: vec![1],
+            }),
+        );
+        assert!(apply_loaded(&mut data_store, loaded_user));
+        assert_eq!(data_store.get_post_ids(&StoryListType::Top), &[1]);
+        assert!(data_store.get_user("pg").is_some());
+    }
+
+    #[tokio::test]
+    async fn failed_lists_are_recorded() {
+        let list_mock = mock("GET", "/askstories.json")
+            .with_status(500)
+            .expect(1)
+            .create();
+
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        load_post_ids(&make_client(), StoryListType::Ask, sender);
+        let mut data_store = DataStore::init();
+        let loaded = receiver.recv().await.unwrap();
+        assert!(matches!(loaded, Loaded::PostIds(_, Err(_))));
+        // The list page shows the failure rather than the error ending the app
+        assert!(apply_loaded(&mut data_store, loaded));
+        list_mock.assert();
+        assert!(!data_store.has_post_ids(&StoryListType::Ask));
+        assert!(data_store
+            .get_failed_post_ids(&StoryListType::Ask)
+            .is_some());
+
+        let loaded = Loaded::PostIds(StoryListType::Ask, Ok(vec![1]));
+        assert!(apply_loaded(&mut data_store, loaded));
+        assert!(data_store
+            .get_failed_post_ids(&StoryListType::Ask)
+            .is_none());
+    }
+}
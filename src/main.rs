@@ -1,116 +1,897 @@
+mod algolia_client;
+mod backend;
+mod browser;
 mod constants;
 mod hn_client;
+mod loader;
 mod stores;
 mod ui;
+mod updates;
 
 use std::io::{self, Write};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use crossterm::{queue, style::Print, terminal::size};
+use crossterm::{cursor, queue, style::Print, terminal::size};
+use std::sync::Arc;
+use tokio::sync::mpsc::{self, UnboundedSender};
 
-use constants::{PAGE_SIZE, POST_ROW_SIZE};
-use stores::data::DataStore;
+use algolia_client::{AlgoliaClient, AlgoliaClientConfig};
+use backend::{BackendKind, HnBackend};
+use browser::{CommandLauncher, Launcher, LauncherConfig};
+use constants::{
+    COMMENT_ROW_SIZE, COMMENT_TREE_MAX_CHILDREN, COMMENT_TREE_MAX_DEPTH, COMMENT_TREE_MAX_ITEMS,
+    LEFT_OFFSET, PAGE_SIZE, POST_ROW_SIZE, TICK_MILLIS, UPDATE_INTERVAL_SECS, USER_HEADER_SIZE,
+};
+use hn_client::{
+    CommentTreeLimits, FetchHandle, FixtureBackend, HnClient, HnClientConfig, Transport,
+};
+use loader::Loaded;
+use stores::data::{CommentRow, DataStore};
 use stores::view::{Page, ScrollDirection, StoryListType, ViewState};
+use updates::UpdateCheck;
 
 enum AsyncAction {
     Noop,
     FetchPosts { filter: StoryListType, offset: u32 },
-    FetchComments { comment_ids: Vec<u32> },
+    FetchComments { parent: u32, comment_ids: Vec<u32> },
+    FetchPollOptions { poll: u32 },
+    FetchUser { user: String, offset: u32 },
+    FetchItem { item: u32 },
+    FetchUpdates,
 }
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let mut stdout = io::stdout();
-    ui::initialize_screen(&mut stdout)?;
-    let mut view_state = ViewState::init();
-    let mut data_store = DataStore::init();
-    let mut async_action: AsyncAction = AsyncAction::FetchPosts {
-        filter: StoryListType::Top,
-        offset: 0,
+// The page of PAGE_SIZE ids starting at `offset`
+fn paginate(ids: &[u32], offset: u32) -> &[u32] {
+    let start = (offset as usize).min(ids.len());
+    let end = (offset as usize + PAGE_SIZE as usize).min(ids.len());
+    &ids[start..end]
+}
+
+// The page showing a loaded item, along with the data it needs
+// Poll options are shown on their poll's page
+fn item_page(data_store: &DataStore, item_id: &u32) -> Option<(Page, AsyncAction)> {
+    if let Some(post) = data_store.get_post(item_id) {
+        let page = Page::PostDetails {
+            post: post.id,
+            cursor_index: 0,
+        };
+        let action = AsyncAction::FetchComments {
+            parent: post.id,
+            comment_ids: post.children.clone(),
+        };
+        Some((page, action))
+    } else if let Some(job) = data_store.get_job(item_id) {
+        Some((Page::JobDetails { job: job.id }, AsyncAction::Noop))
+    } else if let Some(poll) = data_store.get_poll(item_id) {
+        let page = Page::PollDetails { poll: poll.id };
+        Some((page, AsyncAction::FetchPollOptions { poll: poll.id }))
+    } else if let Some(comment) = data_store.get_comment(item_id) {
+        let page = Page::CommentDetails {
+            comment: comment.id,
+            cursor_index: 0,
+        };
+        let action = AsyncAction::FetchComments {
+            parent: comment.id,
+            comment_ids: comment.children.clone(),
+        };
+        Some((page, action))
+    } else if let Some(option) = data_store.get_poll_option(item_id) {
+        item_page(data_store, &option.poll).or_else(|| {
+            let page = Page::OpenItem { item: option.poll };
+            Some((page, AsyncAction::FetchItem { item: option.poll }))
+        })
+    } else {
+        None
+    }
+}
+
+// Opens the page for an item of any kind, returning the data it needs
+// Items that have not been loaded are loaded first to find out their kind
+fn open_item(view_state: &mut ViewState, data_store: &DataStore, item_id: &u32) -> AsyncAction {
+    match item_page(data_store, item_id) {
+        Some((page, action)) => {
+            view_state.navigate(page);
+            action
+        }
+        None => {
+            view_state.navigate(Page::OpenItem { item: *item_id });
+            AsyncAction::FetchItem { item: *item_id }
+        }
+    }
+}
+
+// The post or comment whose replies are shown on the current page, with its replies
+fn open_thread<'a>(view_state: &ViewState, data_store: &'a DataStore) -> Option<(u32, &'a [u32])> {
+    match &view_state.page {
+        Page::PostDetails { post, .. } => data_store
+            .get_post(post)
+            .map(|post| (post.id, &post.children[..])),
+        Page::CommentDetails { comment, .. } => data_store
+            .get_comment(comment)
+            .map(|comment| (comment.id, &comment.children[..])),
+        _ => None,
+    }
+}
+
+// The id of the post or comment whose replies are shown, loaded or not
+fn open_thread_id(view_state: &ViewState) -> Option<u32> {
+    match &view_state.page {
+        Page::PostDetails { post, .. } => Some(*post),
+        Page::CommentDetails { comment, .. } => Some(*comment),
+        _ => None,
+    }
+}
+
+// The links in the focused text - the selected comment, or the text of the story,
+// comment, job or poll the page is about when no comment is selected
+fn focused_links(view_state: &ViewState, data_store: &DataStore) -> Vec<String> {
+    let selected =
+        match &view_state.page {
+            Page::PostDetails { cursor_index, .. } | Page::CommentDetails { cursor_index, .. } => {
+                open_thread(view_state, data_store).and_then(|(parent, children)| match data_store
+                    .get_comment_tree(parent, children)
+                    .into_iter()
+                    .nth(*cursor_index as usize)
+                {
+                    Some(CommentRow::Comment { comment, .. }) => Some(comment),
+                    _ => None,
+                })
+            }
+            _ => None,
+        };
+    let text = match selected {
+        Some(comment) if comment.deleted || comment.dead => None,
+        Some(comment) => comment.text.as_deref(),
+        None => match &view_state.page {
+            Page::PostDetails { post, .. } => data_store
+                .get_post(post)
+                .and_then(|post| post.text.as_deref()),
+            Page::CommentDetails { comment, .. } => data_store
+                .get_comment(comment)
+                .and_then(|comment| comment.text.as_deref()),
+            Page::JobDetails { job } => data_store.get_job(job).and_then(|job| job.text.as_deref()),
+            Page::PollDetails { poll } => data_store
+                .get_poll(poll)
+                .and_then(|poll| poll.text.as_deref()),
+            _ => None,
+        },
     };
+    text.map_or_else(Vec::new, ui::extract_links)
+}
 
-    loop {
-        match &async_action {
-            AsyncAction::FetchPosts { filter, offset } => {
-                // Only load posts if this has not been added to the store
-                if !data_store.has_post_ids(filter) {
-                    // TODO - add in loading screen state
-                    let post_ids = hn_client::get_post_ids(filter).await?;
-                    data_store.hydrate_post_ids(filter, post_ids);
-                }
+// The selected story or submission, or the item the page is about
+fn selected_item(view_state: &ViewState, data_store: &DataStore) -> Option<u32> {
+    match &view_state.page {
+        Page::PostList {
+            offset,
+            cursor_index,
+            filter,
+        } => data_store
+            .get_post_ids(filter)
+            .get((offset + cursor_index) as usize)
+            .copied(),
+        Page::UserProfile {
+            user,
+            offset,
+            cursor_index,
+        } => data_store.get_user(user).and_then(|user| {
+            paginate(&user.submitted, *offset)
+                .get(*cursor_index as usize)
+                .copied()
+        }),
+        Page::PostDetails { post, .. } => Some(*post),
+        Page::CommentDetails { comment, .. } => Some(*comment),
+        Page::JobDetails { job } => Some(*job),
+        Page::PollDetails { poll } => Some(*poll),
+        Page::OpenItem { item } => Some(*item),
+    }
+}
 
-                let post_ids = data_store.get_post_ids(filter);
-                let start = *offset as usize;
-                let end = (*offset + PAGE_SIZE as u32) as usize;
-                let paginated_post_ids = &post_ids[start..end];
-                let unloaded_post_ids = data_store.get_missing_post_ids(paginated_post_ids);
+// The url of a story or job, or its discussion if it has none e.g. Ask HN posts
+fn item_url(data_store: &DataStore, item_id: &u32) -> String {
+    data_store
+        .get_post(item_id)
+        .and_then(|post| post.url.clone())
+        .or_else(|| data_store.get_job(item_id).and_then(|job| job.url.clone()))
+        .unwrap_or_else(|| browser::discussion_url(*item_id))
+}
 
-                if unloaded_post_ids.len() > 0 {
-                    // TODO - add in loading screen state
-                    let posts = hn_client::get_stories(&unloaded_post_ids).await?;
-                    data_store.hydrate_posts(posts);
-                }
+// Moves between pages of submissions on the user profile, returning the data it needs
+fn turn_user_page(
+    view_state: &mut ViewState,
+    data_store: &DataStore,
+    direction: ScrollDirection,
+) -> AsyncAction {
+    if let Page::UserProfile { user, .. } = &view_state.page {
+        let total = data_store
+            .get_user(user)
+            .map_or(0, |user| user.submitted.len() as u32);
+        view_state.turn_page(total, direction);
+    }
+    match &view_state.page {
+        Page::UserProfile { user, offset, .. } => AsyncAction::FetchUser {
+            user: user.clone(),
+            offset: *offset,
+        },
+        _ => AsyncAction::Noop,
+    }
+}
+
+// One line summary of a submission on the user profile
+fn describe_submission(data_store: &DataStore, item_id: &u32) -> String {
+    if let Some(post) = data_store.get_post(item_id) {
+        let title = ui::story_title(&post.title, post.dead, post.deleted);
+        format!("{} - {} comments", title, post.descendants)
+    } else if let Some(job) = data_store.get_job(item_id) {
+        let title = ui::story_title(&job.title, job.dead, job.deleted);
+        format!("[job] {}", title)
+    } else if let Some(poll) = data_store.get_poll(item_id) {
+        let title = ui::story_title(&poll.title, poll.dead, poll.deleted);
+        format!("[poll] {} - {} comments", title, poll.descendants)
+    } else if let Some(comment) = data_store.get_comment(item_id) {
+        match &comment.text {
+            Some(text) if !comment.deleted && !comment.dead => {
+                let text = ui::decode_html(text);
+                format!(
+                    "[comment] {}",
+                    text.split_whitespace().collect::<Vec<&str>>().join(" ")
+                )
+            }
+            _ => String::from("[comment] [deleted]"),
+        }
+    } else if let Some(error) = data_store.get_failed_item(item_id) {
+        format!("[failed to load: {}] - r to retry", error)
+    } else {
+        String::from("Loading...")
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+// The data the current page needs, only what is missing from the store is loaded
+fn page_fetch(view_state: &ViewState, data_store: &DataStore) -> AsyncAction {
+    match &view_state.page {
+        Page::PostList { offset, filter, .. } => AsyncAction::FetchPosts {
+            filter: *filter,
+            offset: *offset,
+        },
+        Page::PostDetails { .. } | Page::CommentDetails { .. } => {
+            match open_thread(view_state, data_store) {
+                Some((parent, children)) => AsyncAction::FetchComments {
+                    parent,
+                    comment_ids: children.to_vec(),
+                },
+                None => AsyncAction::Noop,
+            }
+        }
+        Page::PollDetails { poll } => AsyncAction::FetchPollOptions { poll: *poll },
+        Page::OpenItem { item } => AsyncAction::FetchItem { item: *item },
+        Page::UserProfile { user, offset, .. } => AsyncAction::FetchUser {
+            user: user.clone(),
+            offset: *offset,
+        },
+        Page::JobDetails { .. } => AsyncAction::Noop,
+    }
+}
+
+// Starts loading the data for `action` in the background, results are sent to `sender`
+// Returns None if there is nothing to load
+fn start_fetch(
+    action: AsyncAction,
+    backend: &Arc<dyn HnBackend>,
+    algolia_client: Option<&Arc<AlgoliaClient>>,
+    data_store: &DataStore,
+    sender: &UnboundedSender<Loaded>,
+) -> Option<FetchHandle> {
+    match action {
+        AsyncAction::FetchPosts { filter, offset } => {
+            // The stories are loaded once the list has been added to the store
+            if !data_store.has_post_ids(&filter) {
+                return Some(loader::load_post_ids(backend, filter, sender.clone()));
+            }
+            let paginated_post_ids = paginate(data_store.get_post_ids(&filter), offset);
+            let unloaded_post_ids = data_store.get_missing_post_ids(paginated_post_ids);
+            if unloaded_post_ids.is_empty() {
+                return None;
             }
-            AsyncAction::FetchComments { comment_ids } => {
-                let unloaded_comment_ids = data_store.get_missing_comment_ids(comment_ids);
+            Some(loader::load_stories(
+                backend,
+                unloaded_post_ids,
+                sender.clone(),
+            ))
+        }
+        AsyncAction::FetchComments {
+            parent,
+            comment_ids,
+        } => {
+            let unloaded_comment_ids = data_store.get_missing_comment_ids(&comment_ids);
+            if unloaded_comment_ids.is_empty() {
+                return None;
+            }
+            let limits = CommentTreeLimits {
+                max_depth: COMMENT_TREE_MAX_DEPTH,
+                max_children: COMMENT_TREE_MAX_CHILDREN,
+                max_items: COMMENT_TREE_MAX_ITEMS,
+            };
+            Some(loader::load_comment_tree(
+                backend,
+                algolia_client,
+                parent,
+                unloaded_comment_ids,
+                limits,
+                sender.clone(),
+            ))
+        }
+        AsyncAction::FetchPollOptions { poll } => {
+            let unloaded_option_ids = data_store
+                .get_poll(&poll)
+                .map(|poll| data_store.get_missing_poll_option_ids(poll))
+                .unwrap_or_default();
+            if unloaded_option_ids.is_empty() {
+                return None;
+            }
+            Some(loader::load_poll_options(
+                backend,
+                unloaded_option_ids,
+                sender.clone(),
+            ))
+        }
+        AsyncAction::FetchUser { user, offset } => {
+            // The submissions are loaded once the user has been added to the store
+            let unloaded_item_ids = match data_store.get_user(&user) {
+                Some(user) => data_store.get_missing_item_ids(paginate(&user.submitted, offset)),
+                None => return Some(loader::load_user(backend, user, sender.clone())),
+            };
+            if unloaded_item_ids.is_empty() {
+                return None;
+            }
+            Some(loader::load_submissions(
+                backend,
+                unloaded_item_ids,
+                sender.clone(),
+            ))
+        }
+        AsyncAction::FetchItem { item } => Some(loader::load_item(backend, item, sender.clone())),
+        AsyncAction::FetchUpdates => Some(updates::load_updates(
+            backend,
+            UpdateCheck::init(data_store),
+            sender.clone(),
+        )),
+        AsyncAction::Noop => None,
+    }
+}
 
-                if unloaded_comment_ids.len() > 0 {
-                    // TODO add loading screen state
-                    let comments = hn_client::get_comments(comment_ids).await?;
-                    data_store.hydrate_comments(comments);
+// Number of rows the cursor can move through on the current page
+fn get_item_count(view_state: &ViewState, data_store: &DataStore) -> u32 {
+    match &view_state.page {
+        Page::PostList { filter, .. } => data_store.get_post_ids(filter).len() as u32,
+        Page::PostDetails { .. } | Page::CommentDetails { .. } => {
+            match open_thread(view_state, data_store) {
+                Some((parent, children)) => {
+                    data_store.get_comment_tree(parent, children).len() as u32
                 }
+                None => 0,
             }
-            AsyncAction::Noop => {}
+        }
+        Page::UserProfile { user, offset, .. } => match data_store.get_user(user) {
+            Some(user) => paginate(&user.submitted, *offset).len() as u32,
+            None => 0,
+        },
+        Page::JobDetails { .. } | Page::PollDetails { .. } | Page::OpenItem { .. } => 0,
+    }
+}
+
+// Prints the rows of a comment tree below a details header
+fn render_comment_tree<W>(
+    w: &mut W,
+    view_state: &ViewState,
+    cursor_index: u32,
+    tree: Vec<CommentRow>,
+) -> crossterm::Result<()>
+where
+    W: Write,
+{
+    let (columns, rows) = size()?;
+    let number_of_comments =
+        rows.saturating_sub(view_state.details_header_size() + 1) / COMMENT_ROW_SIZE as u16 + 1;
+    for (i, row) in tree
+        .into_iter()
+        .skip(view_state.scroll_offset as usize)
+        .take(number_of_comments as usize)
+        .enumerate()
+    {
+        let n = i + view_state.scroll_offset as usize;
+        let cursor_text = if cursor_index as usize == n {
+            "➜  "
+        } else {
+            "   "
         };
-        async_action = AsyncAction::Noop;
+        queue!(w, Print(cursor_text))?;
+        match row {
+            CommentRow::Comment { depth, comment } => {
+                ui::print_comment(w, depth, columns, comment)?
+            }
+            CommentRow::MoreReplies { depth, count, .. } => {
+                ui::print_more_replies(w, depth, columns, count)?
+            }
+            CommentRow::Failed {
+                depth, id, error, ..
+            } => ui::print_failed_comment(w, depth, columns, id, error)?,
+        }
+    }
+    Ok(())
+}
 
-        ui::clear_screen(&mut stdout)?;
+fn render<W>(w: &mut W, view_state: &ViewState, data_store: &DataStore) -> crossterm::Result<()>
+where
+    W: Write,
+{
+    ui::clear_screen(w)?;
 
-        let (columns, rows) = size()?;
-        match &view_state.page {
-            Page::PostList {
-                cursor_index,
-                filter,
-                ..
-            } => {
-                // Calculate number of posts that can fit in the terminal
-                // Remove from total rows - end, etc - 1 row for commands
-                // Add one to handle render overflows
-                let number_of_posts = (rows - 1) / POST_ROW_SIZE as u16 + 1;
-                for (i, post_id) in data_store
-                    .get_post_ids(filter)
+    let (columns, rows) = size()?;
+    match &view_state.page {
+        Page::PostList {
+            cursor_index,
+            filter,
+            ..
+        } => {
+            // Calculate number of posts that can fit in the terminal
+            // Remove from total rows - end, etc - 1 row for commands
+            // Add one to handle render overflows
+            let number_of_posts = (rows - 1) / POST_ROW_SIZE as u16 + 1;
+            for (i, post_id) in data_store
+                .get_post_ids(filter)
+                .iter()
+                .skip(view_state.scroll_offset as usize)
+                .take(number_of_posts as usize)
+                .enumerate()
+            {
+                let n = i + view_state.scroll_offset as usize;
+                let cursor_text = if *cursor_index as usize == n {
+                    "➜  "
+                } else {
+                    "   "
+                };
+                queue!(w, Print(cursor_text))?;
+                if let Some(post) = data_store.get_post(post_id) {
+                    ui::print_post(w, n, columns, post)?;
+                } else if let Some(job) = data_store.get_job(post_id) {
+                    ui::print_job(w, n, columns, job)?;
+                } else if let Some(poll) = data_store.get_poll(post_id) {
+                    ui::print_poll(w, n, columns, poll)?;
+                } else {
+                    let text = match data_store.get_failed_item(post_id) {
+                        Some(error) => format!("[failed to load: {}] - r to retry", error),
+                        None => String::from("Loading..."),
+                    };
+                    ui::print_post_placeholder(w, n, columns, &text)?;
+                }
+            }
+            if let Some(error) = data_store.get_failed_post_ids(filter) {
+                let text = format!("[failed to load: {}] - r to retry", error);
+                queue!(w, cursor::MoveRight(LEFT_OFFSET), Print(text))?;
+            }
+            ui::print_list_footer(w, rows, filter)?;
+        }
+        Page::JobDetails { job } => {
+            if let Some(job) = data_store.get_job(job) {
+                ui::print_job_details(w, columns, rows, job)?;
+            }
+        }
+        Page::PollDetails { poll } => {
+            if let Some(poll) = data_store.get_poll(poll) {
+                let options = data_store.get_poll_options(poll);
+                ui::print_poll_details(w, columns, rows, poll, &options)?;
+            }
+        }
+        Page::UserProfile {
+            user,
+            offset,
+            cursor_index,
+        } => match data_store.get_user(user) {
+            Some(user) => {
+                ui::print_user_header(w, columns, user, now())?;
+
+                let number_of_submissions = rows.saturating_sub(USER_HEADER_SIZE as u16 + 1);
+                for (i, item_id) in paginate(&user.submitted, *offset)
                     .iter()
                     .skip(view_state.scroll_offset as usize)
-                    .take(number_of_posts as usize)
+                    .take(number_of_submissions as usize)
                     .enumerate()
                 {
                     let n = i + view_state.scroll_offset as usize;
-                    // TODO create a render page post list fn
-                    // TODO handle error case here
-                    let post = data_store.get_post(post_id).unwrap();
                     let cursor_text = if *cursor_index as usize == n {
                         "➜  "
                     } else {
                         "   "
                     };
-                    queue!(stdout, Print(cursor_text))?;
-                    ui::print_post(&mut stdout, n, columns, post)?;
+                    queue!(w, Print(cursor_text))?;
+                    let text = describe_submission(data_store, item_id);
+                    ui::print_submission(w, *offset as usize + n, columns, &text)?;
                 }
+                ui::print_user_footer(w, rows, *offset, user.submitted.len())?;
+            }
+            None => {
+                let text = match data_store.get_failed_user(user) {
+                    Some(error) => format!("[failed to load {}: {}] - r to retry", user, error),
+                    None => String::from("Loading..."),
+                };
+                queue!(w, cursor::MoveRight(LEFT_OFFSET), Print(text))?;
+            }
+        },
+        Page::PostDetails { post, cursor_index } => {
+            if let Some(post) = data_store.get_post(post) {
+                ui::print_post_header(w, columns, post)?;
+                let tree = data_store.get_comment_tree(post.id, &post.children);
+                render_comment_tree(w, view_state, *cursor_index, tree)?;
+            }
+        }
+        Page::CommentDetails {
+            comment,
+            cursor_index,
+        } => {
+            if let Some(comment) = data_store.get_comment(comment) {
+                let (ancestors, missing) = data_store.get_ancestors(comment);
+                let status = missing.map(|parent| match data_store.get_failed_item(&parent) {
+                    Some(error) => format!("[context failed to load: {}] - r to retry", error),
+                    None => String::from("Loading context..."),
+                });
+                ui::print_context(w, columns, &ancestors, status.as_deref())?;
+                ui::print_comment_header(w, columns, comment)?;
+                let tree = data_store.get_comment_tree(comment.id, &comment.children);
+                render_comment_tree(w, view_state, *cursor_index, tree)?;
+            }
+        }
+        Page::OpenItem { item } => {
+            let text = match data_store.get_failed_item(item) {
+                Some(error) => format!("[failed to load item {}: {}] - r to retry", item, error),
+                None => format!("Loading item {}...", item),
+            };
+            queue!(w, cursor::MoveRight(LEFT_OFFSET), Print(text))?;
+        }
+    };
+    ui::print_links_footer(w, columns, rows, &focused_links(view_state, data_store))?;
+    w.flush()?;
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let hn_client = Arc::new(HnClient::init(HnClientConfig::from_env())?);
+    let algolia_config = AlgoliaClientConfig::from_env();
+    let comment_trees = algolia_config.comment_trees;
+    let algolia_client = Arc::new(AlgoliaClient::init(algolia_config, hn_client.http_client()));
+    let backend_kind = BackendKind::from_env();
+    let backend: Arc<dyn HnBackend> = match &backend_kind {
+        BackendKind::Firebase => hn_client.clone(),
+        BackendKind::Algolia => algolia_client.clone(),
+        BackendKind::Fixture(dir) => Arc::new(FixtureBackend::load(dir)?),
+    };
+    let is_firebase = matches!(backend_kind, BackendKind::Firebase);
+    // Comment trees are loaded from the HN Search API ahead of the HN API if enabled
+    let comment_tree_client = if comment_trees && is_firebase {
+        Some(algolia_client)
+    } else {
+        None
+    };
+    let launcher: Box<dyn Launcher> = Box::new(CommandLauncher::init(LauncherConfig::from_env()));
+    // Shown until the next key is pressed
+    let mut launch_error: Option<io::Error> = None;
+    let mut stdout = io::stdout();
+    ui::initialize_screen(&mut stdout)?;
+    let mut view_state = ViewState::init();
+    let mut data_store = DataStore::init();
+    let mut async_action: AsyncAction = AsyncAction::FetchPosts {
+        filter: StoryListType::Top,
+        offset: 0,
+    };
+    // Event streams are only available from the HN API
+    let streaming = is_firebase && hn_client.transport() == Transport::Stream;
+    // Fetches and streamed changes run in the background and send what they load here,
+    // so a slow request never blocks input
+    let (loaded_sender, mut loaded_receiver) = mpsc::unbounded_channel();
+    // Comment fetches along with the post they were started for
+    let mut comment_fetches: Vec<(u32, FetchHandle)> = Vec::new();
+    let mut item_watch: Option<(u32, FetchHandle)> = None;
+    // Loads the parents of the open comment, along with the comment it was started for
+    let mut context_fetch: Option<(u32, FetchHandle)> = None;
+    if streaming {
+        updates::spawn_watch_post_ids(&hn_client, StoryListType::Top, loaded_sender.clone());
+    }
+    let update_interval = Duration::from_secs(UPDATE_INTERVAL_SECS);
+    let mut next_update = Instant::now() + update_interval;
+    let mut checking_updates = false;
+    let mut needs_render = true;
+    let mut show_debug = false;
+
+    loop {
+        let mut needs_page_data = false;
+        while let Ok(loaded) = loaded_receiver.try_recv() {
+            if let Loaded::Updates(_) = loaded {
+                checking_updates = false;
+            }
+            needs_page_data |= loader::apply_loaded(&mut data_store, loaded);
+            needs_render = true;
+        }
+        // Story lists and users are loaded before the items they list, and opened items
+        // are shown on the page for their kind once they have loaded
+        let page_action = match &view_state.page {
+            // Failed lists are only loaded again on refresh
+            Page::PostList { filter, .. }
+                if needs_page_data && data_store.get_failed_post_ids(filter).is_some() =>
+            {
+                AsyncAction::Noop
             }
-            Page::PostDetails { post, cursor_index } => {
-                // TODO - implement
+            Page::PostList { .. } | Page::UserProfile { .. } if needs_page_data => {
+                page_fetch(&view_state, &data_store)
             }
+            Page::OpenItem { item } => match item_page(&data_store, item) {
+                Some((page, action)) => {
+                    view_state.redirect(page);
+                    action
+                }
+                None => AsyncAction::Noop,
+            },
+            _ => AsyncAction::Noop,
         };
-        stdout.flush()?;
 
-        match ui::get_user_action()? {
+        let open_post = open_thread_id(&view_state);
+        let actions = vec![
+            std::mem::replace(&mut async_action, AsyncAction::Noop),
+            page_action,
+        ];
+        for action in actions {
+            let is_comment_fetch = matches!(action, AsyncAction::FetchComments { .. });
+            let fetch_handle = start_fetch(
+                action,
+                &backend,
+                comment_tree_client.as_ref(),
+                &data_store,
+                &loaded_sender,
+            );
+            match (fetch_handle, open_post) {
+                (Some(fetch_handle), Some(post)) if is_comment_fetch => {
+                    comment_fetches.push((post, fetch_handle))
+                }
+                _ => {}
+            }
+        }
+
+        if needs_render {
+            render(&mut stdout, &view_state, &data_store)?;
+            if show_debug {
+                let (columns, _) = size()?;
+                let concurrency = backend
+                    .concurrency_limit()
+                    .map_or(String::from("-"), |limit| limit.to_string());
+                let lines = vec![format!("concurrency limit: {}", concurrency)];
+                ui::print_debug_overlay(&mut stdout, columns, &lines)?;
+            }
+            if let Some(error) = &launch_error {
+                let (columns, _) = size()?;
+                let lines = vec![format!("failed to open link: {}", error)];
+                ui::print_debug_overlay(&mut stdout, columns, &lines)?;
+            }
+        }
+        let (_, rows) = size()?;
+
+        let item_count = get_item_count(&view_state, &data_store);
+        // Input is polled on each tick so loaded data is shown as it arrives
+        let user_action = ui::get_user_action(Duration::from_millis(TICK_MILLIS))?;
+        needs_render = !matches!(user_action, ui::UserAction::Tick);
+        if needs_render {
+            launch_error = None;
+        }
+        match user_action {
             ui::UserAction::Quit => break,
-            ui::UserAction::Up => view_state.scroll(rows, ScrollDirection::Up),
-            ui::UserAction::Down => view_state.scroll(rows, ScrollDirection::Down),
-            // ui::UserAction::Enter => {async_action = AsyncAction::Noop;}
-            _ => {
-                // TO IMPLEMENT
+            ui::UserAction::Up => view_state.scroll(rows, item_count, ScrollDirection::Up),
+            ui::UserAction::Down => view_state.scroll(rows, item_count, ScrollDirection::Down),
+            ui::UserAction::Enter => match &view_state.page {
+                Page::PostList {
+                    offset,
+                    cursor_index,
+                    filter,
+                } => {
+                    if let Some(story_id) = data_store
+                        .get_post_ids(filter)
+                        .get((offset + cursor_index) as usize)
+                    {
+                        async_action = open_item(&mut view_state, &data_store, story_id);
+                    }
+                }
+                Page::UserProfile {
+                    user,
+                    offset,
+                    cursor_index,
+                } => {
+                    if let Some(item_id) = data_store.get_user(user).and_then(|user| {
+                        paginate(&user.submitted, *offset).get(*cursor_index as usize)
+                    }) {
+                        async_action = open_item(&mut view_state, &data_store, item_id);
+                    }
+                }
+                Page::PostDetails { cursor_index, .. }
+                | Page::CommentDetails { cursor_index, .. } => {
+                    // Load the remaining or failed replies when a "more replies" or
+                    // failed row is selected
+                    let selected_parent =
+                        open_thread(&view_state, &data_store).and_then(|(parent, children)| {
+                            match data_store
+                                .get_comment_tree(parent, children)
+                                .into_iter()
+                                .nth(*cursor_index as usize)
+                            {
+                                Some(CommentRow::MoreReplies { parent, .. }) => Some(parent),
+                                Some(CommentRow::Failed { parent, .. }) => Some(parent),
+                                _ => None,
+                            }
+                        });
+                    let children =
+                        selected_parent.and_then(|parent| match data_store.get_comment(&parent) {
+                            Some(comment) => Some(&comment.children),
+                            None => data_store.get_post(&parent).map(|post| &post.children),
+                        });
+                    if let (Some(parent), Some(children)) = (selected_parent, children) {
+                        async_action = AsyncAction::FetchComments {
+                            parent,
+                            comment_ids: children.clone(),
+                        };
+                    }
+                }
+                Page::JobDetails { .. } | Page::PollDetails { .. } | Page::OpenItem { .. } => {}
+            },
+            ui::UserAction::Back => {
+                // Loads anything that was cancelled when the page was left
+                view_state.back();
+                async_action = page_fetch(&view_state, &data_store);
+            }
+            // Only posts and comments that are missing (i.e. failed) are fetched again
+            ui::UserAction::Refresh => {
+                async_action = page_fetch(&view_state, &data_store);
+                // Parents that failed to load are loaded again below
+                context_fetch = None;
+            }
+            ui::UserAction::SelectList(filter) => {
+                if let Page::PostList { .. } = view_state.page {
+                    view_state.select_list(filter);
+                    async_action = AsyncAction::FetchPosts { filter, offset: 0 };
+                }
+            }
+            ui::UserAction::OpenUser => {
+                // The author of the selected story or comment
+                let selected_user = match &view_state.page {
+                    Page::PostList {
+                        offset,
+                        cursor_index,
+                        filter,
+                    } => data_store
+                        .get_post_ids(filter)
+                        .get((offset + cursor_index) as usize)
+                        .and_then(|id| {
+                            data_store
+                                .get_post(id)
+                                .map(|post| &post.by)
+                                .or_else(|| data_store.get_job(id).map(|job| &job.by))
+                                .or_else(|| data_store.get_poll(id).map(|poll| &poll.by))
+                        })
+                        .cloned(),
+                    Page::PostDetails { cursor_index, .. }
+                    | Page::CommentDetails { cursor_index, .. } => {
+                        open_thread(&view_state, &data_store).and_then(|(parent, children)| {
+                            match data_store
+                                .get_comment_tree(parent, children)
+                                .into_iter()
+                                .nth(*cursor_index as usize)
+                            {
+                                Some(CommentRow::Comment { comment, .. }) => comment.by.clone(),
+                                _ => None,
+                            }
+                        })
+                    }
+                    _ => None,
+                };
+                if let Some(user) = selected_user {
+                    view_state.navigate(Page::UserProfile {
+                        user: user.clone(),
+                        offset: 0,
+                        cursor_index: 0,
+                    });
+                    async_action = AsyncAction::FetchUser { user, offset: 0 };
+                }
+            }
+            ui::UserAction::NextPage => {
+                async_action = turn_user_page(&mut view_state, &data_store, ScrollDirection::Down)
             }
+            ui::UserAction::PrevPage => {
+                async_action = turn_user_page(&mut view_state, &data_store, ScrollDirection::Up)
+            }
+            ui::UserAction::Tick => {
+                // Changes are pushed through the event stream when streaming
+                // Only one check for updates runs at a time
+                if !streaming && !checking_updates && Instant::now() >= next_update {
+                    async_action = AsyncAction::FetchUpdates;
+                    checking_updates = true;
+                    next_update = Instant::now() + update_interval;
+                }
+            }
+            ui::UserAction::GoToItem => {
+                let input = ui::read_line(&mut stdout, rows, "go to item (id or url): ")?;
+                if let Some(item_id) = input.as_deref().and_then(hn_client::parse_item_id) {
+                    async_action = open_item(&mut view_state, &data_store, &item_id);
+                }
+            }
+            ui::UserAction::OpenLink(number) => {
+                let links = focused_links(&view_state, &data_store);
+                if let Some(link) = links.get(number - 1) {
+                    match hn_client::parse_item_id(link) {
+                        // HN items are opened in the app
+                        Some(item_id) => {
+                            async_action = open_item(&mut view_state, &data_store, &item_id)
+                        }
+                        None => {
+                            launch_error =
+                                browser::open_url(&mut stdout, launcher.as_ref(), link)?.err()
+                        }
+                    }
+                }
+            }
+            ui::UserAction::OpenUrl => {
+                if let Some(item_id) = selected_item(&view_state, &data_store) {
+                    let url = item_url(&data_store, &item_id);
+                    launch_error = browser::open_url(&mut stdout, launcher.as_ref(), &url)?.err();
+                }
+            }
+            ui::UserAction::OpenDiscussion => {
+                if let Some(item_id) = selected_item(&view_state, &data_store) {
+                    let url = browser::discussion_url(item_id);
+                    launch_error = browser::open_url(&mut stdout, launcher.as_ref(), &url)?.err();
+                }
+            }
+            ui::UserAction::ToggleDebug => show_debug = !show_debug,
+            ui::UserAction::Rerender => {}
+        };
+
+        // Comment fetches are cancelled when their post or comment is no longer open
+        let open_post = open_thread_id(&view_state);
+        comment_fetches.retain(|(post, fetch_handle)| {
+            if Some(*post) == open_post {
+                return true;
+            }
+            fetch_handle.cancel();
+            false
+        });
+        // The parents of the open comment are loaded to show it in context
+        let open_comment = match &view_state.page {
+            Page::CommentDetails { comment, .. } => Some(*comment),
+            _ => None,
         };
+        if context_fetch.as_ref().map(|(comment, _)| *comment) != open_comment {
+            if let Some((_, fetch_handle)) = context_fetch.take() {
+                fetch_handle.cancel();
+            }
+            context_fetch = open_comment.and_then(|comment_id| {
+                let comment = data_store.get_comment(&comment_id)?;
+                let missing = data_store.get_ancestors(comment).1?;
+                let fetch_handle = loader::load_ancestors(&backend, missing, loaded_sender.clone());
+                Some((comment_id, fetch_handle))
+            });
+        }
+        // The open post or comment is streamed so its score and replies stay current
+        if streaming && item_watch.as_ref().map(|(post, _)| *post) != open_post {
+            if let Some((_, fetch_handle)) = item_watch.take() {
+                fetch_handle.cancel();
+            }
+            item_watch = open_post.map(|post| {
+                let fetch_handle =
+                    updates::spawn_watch_item(&hn_client, post, loaded_sender.clone());
+                (post, fetch_handle)
+            });
+        }
     }
     ui::teardown_screen(&mut stdout)?;
 
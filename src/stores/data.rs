@@ -1,5 +1,6 @@
+use crate::constants::MAX_ANCESTORS;
 use crate::stores::view::StoryListType;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 pub struct Post {
     pub id: u32,
@@ -10,58 +11,201 @@ pub struct Post {
     pub url: Option<String>,
     pub text: Option<String>,
     pub descendants: u32,
+    pub score: u16,
+    pub dead: bool,
+    pub deleted: bool,
 }
 
-pub struct Comment {
+// Job postings are listed alongside stories, they either link out with a url
+// or describe the role in text
+pub struct Job {
+    pub id: u32,
+    pub by: String,
+    pub title: String,
+    pub time: u32,
+    pub url: Option<String>,
+    pub text: Option<String>,
+    pub dead: bool,
+    pub deleted: bool,
+}
+
+pub struct Poll {
     pub id: u32,
     pub by: String,
+    // Ids of the poll's options
+    pub parts: Vec<u32>,
+    pub title: String,
+    pub time: u32,
+    pub text: Option<String>,
+    pub score: u16,
+    pub descendants: u32,
+    pub dead: bool,
+    pub deleted: bool,
+}
+
+pub struct PollOption {
+    pub id: u32,
+    // Id of the poll the option belongs to
+    pub poll: u32,
+    pub text: String,
+    pub score: u16,
+}
+
+// An entry in a story list
+pub enum Story {
+    Post(Post),
+    Job(Job),
+    Poll(Poll),
+}
+
+// Deleted comments have no author or text, they are kept so that their
+// replies are still shown
+pub struct Comment {
+    pub id: u32,
+    pub by: Option<String>,
     pub children: Vec<u32>,
     pub parent: u32,
-    pub text: String,
+    pub text: Option<String>,
     pub time: u32,
+    pub dead: bool,
+    pub deleted: bool,
+}
+
+pub struct User {
+    pub id: String,
+    // Unix time the account was created
+    pub created: u32,
+    pub karma: i32,
+    // HTML, as returned by the API
+    pub about: Option<String>,
+    // Ids of the user's stories, polls and comments, newest first
+    pub submitted: Vec<u32>,
+}
+
+// An entry in a user's submissions
+pub enum Submission {
+    Story(Story),
+    Comment(Comment),
+}
+
+// An item of any kind, e.g. one opened by id
+pub enum AnyItem {
+    Post(Post),
+    Job(Job),
+    Poll(Poll),
+    PollOption(PollOption),
+    Comment(Comment),
+}
+
+impl From<Story> for AnyItem {
+    fn from(story: Story) -> AnyItem {
+        match story {
+            Story::Post(post) => AnyItem::Post(post),
+            Story::Job(job) => AnyItem::Job(job),
+            Story::Poll(poll) => AnyItem::Poll(poll),
+        }
+    }
+}
+
+impl From<Submission> for AnyItem {
+    fn from(submission: Submission) -> AnyItem {
+        match submission {
+            Submission::Story(story) => AnyItem::from(story),
+            Submission::Comment(comment) => AnyItem::Comment(comment),
+        }
+    }
+}
+
+// A post, poll or comment above a comment, see `DataStore::get_ancestors`
+pub enum Ancestor<'a> {
+    Post(&'a Post),
+    Poll(&'a Poll),
+    Comment(&'a Comment),
+}
+
+// Marks replies under `parent` that have not been loaded
+pub struct MoreReplies {
+    pub parent: u32,
+    pub count: usize,
+}
+
+// A row in a flattened comment tree, with its depth in the tree
+pub enum CommentRow<'a> {
+    Comment {
+        depth: usize,
+        comment: &'a Comment,
+    },
+    MoreReplies {
+        depth: usize,
+        parent: u32,
+        count: usize,
+    },
+    Failed {
+        depth: usize,
+        id: u32,
+        parent: u32,
+        error: &'a str,
+    },
 }
 
 pub struct DataStore {
-    top_post_ids: Vec<u32>,
-    best_post_ids: Vec<u32>,
-    new_post_ids: Vec<u32>,
+    post_ids: HashMap<StoryListType, Vec<u32>>,
+    // Story lists that failed to load, with the reason
+    failed_post_ids: HashMap<StoryListType, String>,
     posts: HashMap<u32, Post>,
+    jobs: HashMap<u32, Job>,
+    polls: HashMap<u32, Poll>,
+    poll_options: HashMap<u32, PollOption>,
     pub comments: HashMap<u32, Comment>,
+    more_replies: HashMap<u32, usize>,
+    // Posts and comments that failed to load, with the reason
+    failed_items: HashMap<u32, String>,
+    users: HashMap<String, User>,
+    failed_users: HashMap<String, String>,
+    // Highest item id seen from /maxitem.json
+    max_item: Option<u32>,
 }
 
 impl DataStore {
     pub fn init() -> DataStore {
         DataStore {
-            top_post_ids: Vec::new(),
-            best_post_ids: Vec::new(),
-            new_post_ids: Vec::new(),
+            post_ids: HashMap::new(),
+            failed_post_ids: HashMap::new(),
             posts: HashMap::new(),
+            jobs: HashMap::new(),
+            polls: HashMap::new(),
+            poll_options: HashMap::new(),
             comments: HashMap::new(),
+            more_replies: HashMap::new(),
+            failed_items: HashMap::new(),
+            users: HashMap::new(),
+            failed_users: HashMap::new(),
+            max_item: None,
         }
     }
 
+    // True once the list has loaded, even if it is empty
     pub fn has_post_ids(&self, story_type: &StoryListType) -> bool {
-        match story_type {
-            StoryListType::Top => self.top_post_ids.len() > 0,
-            StoryListType::Best => self.best_post_ids.len() > 0,
-            StoryListType::New => self.new_post_ids.len() > 0,
-        }
+        self.post_ids.contains_key(story_type)
     }
 
     pub fn get_post_ids(&self, story_type: &StoryListType) -> &[u32] {
-        match story_type {
-            StoryListType::Top => &self.top_post_ids,
-            StoryListType::Best => &self.best_post_ids,
-            StoryListType::New => &self.new_post_ids,
-        }
+        self.post_ids
+            .get(story_type)
+            .map_or(&[], |post_ids| post_ids)
     }
 
     pub fn hydrate_post_ids(&mut self, story_type: &StoryListType, post_ids: Vec<u32>) {
-        match story_type {
-            StoryListType::Top => self.top_post_ids = post_ids,
-            StoryListType::Best => self.best_post_ids = post_ids,
-            StoryListType::New => self.new_post_ids = post_ids,
-        };
+        self.failed_post_ids.remove(story_type);
+        self.post_ids.insert(*story_type, post_ids);
+    }
+
+    pub fn get_failed_post_ids(&self, story_type: &StoryListType) -> Option<&String> {
+        self.failed_post_ids.get(story_type)
+    }
+
+    pub fn hydrate_failed_post_ids(&mut self, story_type: &StoryListType, error: String) {
+        self.failed_post_ids.insert(*story_type, error);
     }
 
     pub fn get_post(&self, post_id: &u32) -> Option<&Post> {
@@ -70,37 +214,289 @@ impl DataStore {
 
     pub fn hydrate_posts(&mut self, posts: Vec<Post>) {
         for post in posts {
+            self.failed_items.remove(&post.id);
             self.posts.insert(post.id, post);
         }
     }
 
+    pub fn get_job(&self, job_id: &u32) -> Option<&Job> {
+        self.jobs.get(job_id)
+    }
+
+    pub fn hydrate_jobs(&mut self, jobs: Vec<Job>) {
+        for job in jobs {
+            self.failed_items.remove(&job.id);
+            self.jobs.insert(job.id, job);
+        }
+    }
+
+    pub fn get_poll(&self, poll_id: &u32) -> Option<&Poll> {
+        self.polls.get(poll_id)
+    }
+
+    pub fn hydrate_polls(&mut self, polls: Vec<Poll>) {
+        for poll in polls {
+            self.failed_items.remove(&poll.id);
+            self.polls.insert(poll.id, poll);
+        }
+    }
+
+    // Options that have not been loaded are skipped
+    pub fn get_poll_options(&self, poll: &Poll) -> Vec<&PollOption> {
+        poll.parts
+            .iter()
+            .filter_map(|option_id| self.poll_options.get(option_id))
+            .collect()
+    }
+
+    pub fn hydrate_poll_options(&mut self, poll_options: Vec<PollOption>) {
+        for option in poll_options {
+            self.failed_items.remove(&option.id);
+            self.poll_options.insert(option.id, option);
+        }
+    }
+
+    pub fn get_poll_option(&self, option_id: &u32) -> Option<&PollOption> {
+        self.poll_options.get(option_id)
+    }
+
+    pub fn get_missing_poll_option_ids(&self, poll: &Poll) -> Vec<u32> {
+        poll.parts
+            .iter()
+            .cloned()
+            .filter(|option_id| !self.poll_options.contains_key(option_id))
+            .collect()
+    }
+
+    pub fn hydrate_stories(&mut self, stories: Vec<Story>) {
+        let mut posts = Vec::new();
+        let mut jobs = Vec::new();
+        let mut polls = Vec::new();
+        for story in stories {
+            match story {
+                Story::Post(post) => posts.push(post),
+                Story::Job(job) => jobs.push(job),
+                Story::Poll(poll) => polls.push(poll),
+            }
+        }
+        self.hydrate_posts(posts);
+        self.hydrate_jobs(jobs);
+        self.hydrate_polls(polls);
+    }
+
+    pub fn get_user(&self, user_id: &str) -> Option<&User> {
+        self.users.get(user_id)
+    }
+
+    pub fn hydrate_user(&mut self, user: User) {
+        self.failed_users.remove(&user.id);
+        self.users.insert(user.id.clone(), user);
+    }
+
+    pub fn get_failed_user(&self, user_id: &str) -> Option<&String> {
+        self.failed_users.get(user_id)
+    }
+
+    pub fn hydrate_failed_user(&mut self, user_id: &str, error: String) {
+        self.failed_users.insert(user_id.to_string(), error);
+    }
+
+    pub fn hydrate_submissions(&mut self, submissions: Vec<Submission>) {
+        let mut stories = Vec::new();
+        let mut comments = Vec::new();
+        for submission in submissions {
+            match submission {
+                Submission::Story(story) => stories.push(story),
+                Submission::Comment(comment) => comments.push(comment),
+            }
+        }
+        self.hydrate_stories(stories);
+        self.hydrate_comments(comments);
+    }
+
+    pub fn hydrate_item(&mut self, item: AnyItem) {
+        match item {
+            AnyItem::Post(post) => self.hydrate_posts(vec![post]),
+            AnyItem::Job(job) => self.hydrate_jobs(vec![job]),
+            AnyItem::Poll(poll) => self.hydrate_polls(vec![poll]),
+            AnyItem::PollOption(option) => self.hydrate_poll_options(vec![option]),
+            AnyItem::Comment(comment) => self.hydrate_comments(vec![comment]),
+        }
+    }
+
     pub fn get_comment(&self, comment_id: &u32) -> Option<&Comment> {
         self.comments.get(comment_id)
     }
 
     pub fn hydrate_comments(&mut self, comments: Vec<Comment>) {
         for comment in comments {
+            self.failed_items.remove(&comment.id);
             self.comments.insert(comment.id, comment);
         }
     }
 
+    // The loaded posts and comments above `comment`, from the root down, along with the
+    // id of the highest parent that has not been loaded if the chain is incomplete
+    pub fn get_ancestors(&self, comment: &Comment) -> (Vec<Ancestor<'_>>, Option<u32>) {
+        let mut ancestors = Vec::new();
+        let mut parent = comment.parent;
+        let missing = loop {
+            if let Some(comment) = self.comments.get(&parent) {
+                ancestors.push(Ancestor::Comment(comment));
+                parent = comment.parent;
+                if ancestors.len() >= MAX_ANCESTORS {
+                    break None;
+                }
+            } else if let Some(post) = self.posts.get(&parent) {
+                ancestors.push(Ancestor::Post(post));
+                break None;
+            } else if let Some(poll) = self.polls.get(&parent) {
+                ancestors.push(Ancestor::Poll(poll));
+                break None;
+            } else {
+                break Some(parent);
+            }
+        };
+        ancestors.reverse();
+        (ancestors, missing)
+    }
+
+    pub fn get_failed_item(&self, item_id: &u32) -> Option<&String> {
+        self.failed_items.get(item_id)
+    }
+
+    pub fn hydrate_failed_items(&mut self, failed_items: Vec<(u32, String)>) {
+        for (item_id, error) in failed_items {
+            self.failed_items.insert(item_id, error);
+        }
+    }
+
     pub fn get_missing_post_ids(&self, post_ids: &[u32]) -> Vec<u32> {
         post_ids
             .iter()
             .cloned()
-            // TODO handle error checking when add errors into hashmap
-            .filter(|post_id| !self.posts.contains_key(post_id))
+            // Failed posts are included so that they are retried
+            .filter(|post_id| {
+                !self.posts.contains_key(post_id)
+                    && !self.jobs.contains_key(post_id)
+                    && !self.polls.contains_key(post_id)
+            })
+            .collect()
+    }
+
+    // Items of any kind that have not been loaded
+    pub fn get_missing_item_ids(&self, item_ids: &[u32]) -> Vec<u32> {
+        let missing_post_ids = self.get_missing_post_ids(item_ids);
+        self.get_missing_comment_ids(&missing_post_ids)
+    }
+
+    // Items of any kind that have been loaded, e.g. to re-fetch them when they change
+    pub fn get_loaded_item_ids(&self) -> HashSet<u32> {
+        self.posts
+            .keys()
+            .chain(self.jobs.keys())
+            .chain(self.polls.keys())
+            .chain(self.comments.keys())
+            .cloned()
             .collect()
     }
 
+    pub fn get_loaded_user_ids(&self) -> HashSet<String> {
+        self.users.keys().cloned().collect()
+    }
+
+    pub fn get_max_item(&self) -> Option<u32> {
+        self.max_item
+    }
+
+    // Returns true if items have been created since the last max item was seen
+    pub fn hydrate_max_item(&mut self, max_item: u32) -> bool {
+        match self.max_item {
+            Some(previous) if previous >= max_item => false,
+            Some(_) => {
+                self.max_item = Some(max_item);
+                true
+            }
+            None => {
+                self.max_item = Some(max_item);
+                false
+            }
+        }
+    }
+
     pub fn get_missing_comment_ids(&self, comment_ids: &[u32]) -> Vec<u32> {
         comment_ids
             .iter()
             .cloned()
-            // TODO handle error checking when add errors into hashmap
+            // Failed comments are included so that they are retried
             .filter(|comment_id| !self.comments.contains_key(comment_id))
             .collect()
     }
+
+    // Replaces the unloaded reply markers for `parent` and any parents in `more_replies`
+    pub fn hydrate_more_replies(&mut self, parent: u32, more_replies: Vec<MoreReplies>) {
+        self.more_replies.remove(&parent);
+        for more in more_replies {
+            self.more_replies.insert(more.parent, more.count);
+        }
+    }
+
+    // Flattens the loaded comments under `parent` into render order (depth first)
+    // Comments that have not been loaded yet are skipped along with their replies,
+    // comments that failed to load are included as `Failed` rows
+    pub fn get_comment_tree(&self, parent: u32, comment_ids: &[u32]) -> Vec<CommentRow<'_>> {
+        enum Entry<'a> {
+            // depth, comment id, parent id
+            Comment(usize, &'a u32, u32),
+            MoreReplies(usize, u32, usize),
+        }
+        let mut rows = Vec::new();
+        let mut stack: Vec<Entry> = Vec::new();
+        if let Some(count) = self.more_replies.get(&parent) {
+            stack.push(Entry::MoreReplies(0, parent, *count));
+        }
+        stack.extend(
+            comment_ids
+                .iter()
+                .rev()
+                .map(|id| Entry::Comment(0, id, parent)),
+        );
+
+        while let Some(entry) = stack.pop() {
+            match entry {
+                Entry::Comment(depth, comment_id, parent) => {
+                    if let Some(comment) = self.get_comment(comment_id) {
+                        if let Some(count) = self.more_replies.get(comment_id) {
+                            stack.push(Entry::MoreReplies(depth + 1, *comment_id, *count));
+                        }
+                        stack.extend(
+                            comment
+                                .children
+                                .iter()
+                                .rev()
+                                .map(|id| Entry::Comment(depth + 1, id, *comment_id)),
+                        );
+                        rows.push(CommentRow::Comment { depth, comment });
+                    } else if let Some(error) = self.get_failed_item(comment_id) {
+                        rows.push(CommentRow::Failed {
+                            depth,
+                            id: *comment_id,
+                            parent,
+                            error,
+                        });
+                    }
+                }
+                Entry::MoreReplies(depth, parent, count) => {
+                    rows.push(CommentRow::MoreReplies {
+                        depth,
+                        parent,
+                        count,
+                    });
+                }
+            }
+        }
+        rows
+    }
 }
 
 #[cfg(test)]
@@ -112,26 +508,45 @@ mod tests {
     fn make_post(id: u32) -> Post {
         let mut rng = rand::thread_rng();
         Post {
-            id: id,
+            id,
             by: rng.sample_iter(&Alphanumeric).take(30).collect(),
-            children: vec![0; 5].iter().map(|_| rng.gen()).collect(),
+            children: [0; 5].iter().map(|_| rng.gen()).collect(),
             title: rng.sample_iter(&Alphanumeric).take(30).collect(),
             time: rng.gen(),
             url: Some(rng.sample_iter(&Alphanumeric).take(30).collect()),
             text: Some(rng.sample_iter(&Alphanumeric).take(30).collect()),
             descendants: rng.gen(),
+            score: rng.gen(),
+            dead: false,
+            deleted: false,
+        }
+    }
+
+    fn make_job(id: u32) -> Job {
+        let mut rng = rand::thread_rng();
+        Job {
+            id,
+            by: rng.sample_iter(&Alphanumeric).take(30).collect(),
+            title: rng.sample_iter(&Alphanumeric).take(30).collect(),
+            time: rng.gen(),
+            url: None,
+            text: Some(rng.sample_iter(&Alphanumeric).take(30).collect()),
+            dead: false,
+            deleted: false,
         }
     }
 
     fn make_comment(id: u32) -> Comment {
         let mut rng = rand::thread_rng();
         Comment {
-            id: id,
-            by: rng.sample_iter(&Alphanumeric).take(30).collect(),
-            children: vec![0; 5].iter().map(|_| rng.gen()).collect(),
+            id,
+            by: Some(rng.sample_iter(&Alphanumeric).take(30).collect()),
+            children: [0; 5].iter().map(|_| rng.gen()).collect(),
             parent: rng.gen(),
-            text: rng.sample_iter(&Alphanumeric).take(30).collect(),
+            text: Some(rng.sample_iter(&Alphanumeric).take(30).collect()),
             time: rng.gen(),
+            dead: false,
+            deleted: false,
         }
     }
 
@@ -139,16 +554,11 @@ mod tests {
     fn data_store_posts() {
         let mut data_store = DataStore::init();
         let n_posts = 5;
-        let mock_posts: Vec<Post> = (0..n_posts)
-            .collect::<Vec<u32>>()
-            .into_iter()
-            .map(|id| make_post(id))
-            .collect();
+        let mock_posts: Vec<Post> = (0..n_posts).map(make_post).collect();
 
-        for enum_variant in vec![StoryListType::Best, StoryListType::Top, StoryListType::New].iter()
-        {
-            assert_eq!(data_store.has_post_ids(&enum_variant), false);
-            assert_eq!(data_store.get_post_ids(&enum_variant).len(), 0);
+        for enum_variant in StoryListType::ALL.iter() {
+            assert!(!data_store.has_post_ids(enum_variant));
+            assert_eq!(data_store.get_post_ids(enum_variant).len(), 0);
         }
 
         // hydrate store
@@ -156,7 +566,8 @@ mod tests {
         data_store.hydrate_post_ids(&StoryListType::Best, post_ids_to_hydrate);
         data_store.hydrate_posts(mock_posts);
 
-        assert_eq!(data_store.has_post_ids(&StoryListType::Best), true);
+        assert!(data_store.has_post_ids(&StoryListType::Best));
+        assert!(!data_store.has_post_ids(&StoryListType::Ask));
         assert_eq!(
             data_store.get_post_ids(&StoryListType::Best).len() as u32,
             n_posts
@@ -165,7 +576,7 @@ mod tests {
         let post_ids = data_store.get_post_ids(&StoryListType::Best);
         for post_id in post_ids {
             // Should have a valid post stored
-            data_store.get_post(&post_id).unwrap();
+            data_store.get_post(post_id).unwrap();
         }
     }
 
@@ -173,11 +584,7 @@ mod tests {
     fn data_store_comments() {
         let mut data_store = DataStore::init();
         let comment_ids: Vec<u32> = (0..5).collect();
-        let mock_comments: Vec<Comment> = comment_ids
-            .iter()
-            .cloned()
-            .map(|id| make_comment(id))
-            .collect();
+        let mock_comments: Vec<Comment> = comment_ids.iter().cloned().map(make_comment).collect();
         data_store.hydrate_comments(mock_comments);
 
         for comment_id in comment_ids {
@@ -191,12 +598,8 @@ mod tests {
         let mut data_store = DataStore::init();
         let post_ids: Vec<u32> = (0..5).collect();
         let comment_ids: Vec<u32> = (6..10).collect();
-        let mock_posts: Vec<Post> = post_ids.iter().cloned().map(|id| make_post(id)).collect();
-        let mock_comments: Vec<Comment> = comment_ids
-            .iter()
-            .cloned()
-            .map(|id| make_comment(id))
-            .collect();
+        let mock_posts: Vec<Post> = post_ids.iter().cloned().map(make_post).collect();
+        let mock_comments: Vec<Comment> = comment_ids.iter().cloned().map(make_comment).collect();
         data_store.hydrate_posts(mock_posts);
         data_store.hydrate_comments(mock_comments);
 
@@ -209,4 +612,191 @@ mod tests {
         assert_eq!(missing_post_ids[0], 5);
         assert_eq!(missing_comment_ids[0], 10);
     }
+
+    #[test]
+    fn comment_tree_is_depth_first() {
+        let mut data_store = DataStore::init();
+        let mut comments: Vec<Comment> = (0..5).map(make_comment).collect();
+        // 0 -> [1 -> [3], 2], 4 is not part of the tree
+        comments[0].children = vec![1, 2];
+        comments[1].children = vec![3];
+        comments[2].children = vec![];
+        comments[3].children = vec![];
+        data_store.hydrate_comments(comments);
+        data_store.hydrate_failed_items(vec![(99, String::from("error"))]);
+
+        data_store.hydrate_more_replies(
+            99,
+            vec![
+                MoreReplies {
+                    parent: 99,
+                    count: 1,
+                },
+                MoreReplies {
+                    parent: 1,
+                    count: 2,
+                },
+            ],
+        );
+
+        let tree: Vec<(usize, String)> = data_store
+            .get_comment_tree(99, &[0, 99])
+            .into_iter()
+            .map(|row| match row {
+                CommentRow::Comment { depth, comment } => (depth, comment.id.to_string()),
+                CommentRow::MoreReplies { depth, count, .. } => (depth, format!("+{}", count)),
+                CommentRow::Failed { depth, id, .. } => (depth, format!("!{}", id)),
+            })
+            .collect();
+        assert_eq!(
+            tree,
+            vec![
+                (0, "0".to_string()),
+                (1, "1".to_string()),
+                (2, "3".to_string()),
+                (2, "+2".to_string()),
+                (1, "2".to_string()),
+                (0, "!99".to_string()),
+                (0, "+1".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn failed_items_are_cleared_on_hydrate() {
+        let mut data_store = DataStore::init();
+        data_store
+            .hydrate_failed_items(vec![(1, String::from("error")), (2, String::from("error"))]);
+        assert_eq!(data_store.get_missing_post_ids(&[1, 2]), vec![1, 2]);
+
+        data_store.hydrate_posts(vec![make_post(1)]);
+        assert!(data_store.get_failed_item(&1).is_none());
+        assert!(data_store.get_failed_item(&2).is_some());
+        assert_eq!(data_store.get_missing_post_ids(&[1, 2]), vec![2]);
+    }
+
+    #[test]
+    fn data_store_stories() {
+        let mut data_store = DataStore::init();
+        data_store.hydrate_stories(vec![Story::Post(make_post(1)), Story::Job(make_job(2))]);
+
+        assert!(data_store.get_post(&1).is_some());
+        assert!(data_store.get_job(&1).is_none());
+        assert!(data_store.get_job(&2).is_some());
+        assert_eq!(data_store.get_missing_post_ids(&[1, 2, 3]), vec![3]);
+    }
+
+    #[test]
+    fn data_store_polls() {
+        let mut data_store = DataStore::init();
+        let poll = Poll {
+            id: 1,
+            by: String::from("author"),
+            parts: vec![2, 3, 4],
+            title: String::from("poll"),
+            time: 0,
+            text: None,
+            score: 0,
+            descendants: 0,
+            dead: false,
+            deleted: false,
+        };
+        data_store.hydrate_stories(vec![Story::Poll(poll)]);
+        data_store.hydrate_poll_options(
+            [4, 2]
+                .iter()
+                .map(|id| PollOption {
+                    id: *id,
+                    poll: 1,
+                    text: String::from("option"),
+                    score: 0,
+                })
+                .collect(),
+        );
+
+        let poll = data_store.get_poll(&1).unwrap();
+        // Options are returned in the order of the poll's parts
+        let option_ids: Vec<u32> = data_store
+            .get_poll_options(poll)
+            .iter()
+            .map(|option| option.id)
+            .collect();
+        assert_eq!(option_ids, vec![2, 4]);
+        assert_eq!(data_store.get_missing_poll_option_ids(poll), vec![3]);
+        assert!(data_store.get_missing_post_ids(&[1]).is_empty());
+    }
+
+    #[test]
+    fn data_store_users() {
+        let mut data_store = DataStore::init();
+        data_store.hydrate_failed_user("pg", String::from("error"));
+        assert!(data_store.get_user("pg").is_none());
+        assert!(data_store.get_failed_user("pg").is_some());
+
+        data_store.hydrate_user(User {
+            id: String::from("pg"),
+            created: 0,
+            karma: 1,
+            about: None,
+            submitted: vec![1, 2, 3],
+        });
+        assert!(data_store.get_failed_user("pg").is_none());
+        assert_eq!(data_store.get_user("pg").unwrap().submitted, vec![1, 2, 3]);
+
+        data_store.hydrate_submissions(vec![
+            Submission::Story(Story::Post(make_post(1))),
+            Submission::Comment(make_comment(2)),
+        ]);
+        assert!(data_store.get_post(&1).is_some());
+        assert!(data_store.get_comment(&2).is_some());
+        assert_eq!(data_store.get_missing_item_ids(&[1, 2, 3]), vec![3]);
+    }
+
+    #[test]
+    fn ancestors_are_listed_from_the_root() {
+        let mut data_store = DataStore::init();
+        let mut parent = make_comment(2);
+        parent.parent = 1;
+        let mut reply = make_comment(3);
+        reply.parent = 2;
+        let mut focused = make_comment(4);
+        focused.parent = 3;
+        data_store.hydrate_comments(vec![parent, reply]);
+
+        // The post at the root has not been loaded
+        let (ancestors, missing) = data_store.get_ancestors(&focused);
+        assert_eq!(ancestors.len(), 2);
+        assert!(matches!(ancestors[0], Ancestor::Comment(comment) if comment.id == 2));
+        assert_eq!(missing, Some(1));
+
+        data_store.hydrate_posts(vec![make_post(1)]);
+        let (ancestors, missing) = data_store.get_ancestors(&focused);
+        let ids: Vec<u32> = ancestors
+            .iter()
+            .map(|ancestor| match ancestor {
+                Ancestor::Post(post) => post.id,
+                Ancestor::Poll(poll) => poll.id,
+                Ancestor::Comment(comment) => comment.id,
+            })
+            .collect();
+        assert_eq!(ids, vec![1, 2, 3]);
+        assert_eq!(missing, None);
+    }
+
+    #[test]
+    fn loaded_items_and_max_item() {
+        let mut data_store = DataStore::init();
+        data_store.hydrate_posts(vec![make_post(1)]);
+        data_store.hydrate_comments(vec![make_comment(2)]);
+        data_store.hydrate_failed_items(vec![(3, String::from("error"))]);
+        let loaded: HashSet<u32> = vec![1, 2].into_iter().collect();
+        assert_eq!(data_store.get_loaded_item_ids(), loaded);
+
+        // The first max item is only recorded
+        assert!(!data_store.hydrate_max_item(10));
+        assert!(!data_store.hydrate_max_item(10));
+        assert!(!data_store.hydrate_max_item(9));
+        assert!(data_store.hydrate_max_item(11));
+        assert_eq!(data_store.get_max_item(), Some(11));
+    }
 }
@@ -1,16 +1,47 @@
-use crate::constants::{PAGE_SIZE, POST_ROW_SIZE};
+use crate::constants::{
+    COMMENT_ROW_SIZE, CONTEXT_SIZE, DETAILS_HEADER_SIZE, PAGE_SIZE, POST_ROW_SIZE, USER_HEADER_SIZE,
+};
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum StoryListType {
     New,
     Best,
     Top,
+    Ask,
+    Show,
+    Job,
+}
+
+impl StoryListType {
+    pub const ALL: [StoryListType; 6] = [
+        StoryListType::Top,
+        StoryListType::New,
+        StoryListType::Best,
+        StoryListType::Ask,
+        StoryListType::Show,
+        StoryListType::Job,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            StoryListType::New => "new",
+            StoryListType::Best => "best",
+            StoryListType::Top => "top",
+            StoryListType::Ask => "ask",
+            StoryListType::Show => "show",
+            StoryListType::Job => "jobs",
+        }
+    }
 }
 
 pub struct ViewState {
     pub page: Page,
     pub scroll_offset: u16,
+    // Pages navigated away from, along with their scroll offset
+    history: Vec<(Page, u16)>,
 }
 
+#[derive(Clone)]
 pub enum Page {
     PostList {
         offset: u32,
@@ -21,6 +52,27 @@ pub enum Page {
         post: u32,
         cursor_index: u32,
     },
+    JobDetails {
+        job: u32,
+    },
+    PollDetails {
+        poll: u32,
+    },
+    // A comment with its parents collapsed above it and its replies below it
+    CommentDetails {
+        comment: u32,
+        cursor_index: u32,
+    },
+    // An item opened by id, shown until it has loaded and its kind is known
+    OpenItem {
+        item: u32,
+    },
+    // `offset` is the index of the first submission shown
+    UserProfile {
+        user: String,
+        offset: u32,
+        cursor_index: u32,
+    },
 }
 
 pub enum ScrollDirection {
@@ -28,6 +80,19 @@ pub enum ScrollDirection {
     Down,
 }
 
+fn move_cursor(cursor_index: u32, max_index: u32, direction: &ScrollDirection) -> u32 {
+    match direction {
+        ScrollDirection::Up => cursor_index.saturating_sub(1),
+        ScrollDirection::Down => {
+            if cursor_index >= max_index {
+                max_index
+            } else {
+                cursor_index + 1
+            }
+        }
+    }
+}
+
 impl ViewState {
     pub fn init() -> ViewState {
         ViewState {
@@ -37,10 +102,50 @@ impl ViewState {
                 filter: StoryListType::Top,
             },
             scroll_offset: 0,
+            history: Vec::new(),
+        }
+    }
+
+    // Moves to a new page - the current page can be returned to with `back`
+    pub fn navigate(&mut self, page: Page) {
+        let previous_page = std::mem::replace(&mut self.page, page);
+        self.history.push((previous_page, self.scroll_offset));
+        self.scroll_offset = 0;
+    }
+
+    // Replaces the current page without adding it to the history, e.g. once an opened
+    // item has loaded
+    pub fn redirect(&mut self, page: Page) {
+        self.page = page;
+        self.scroll_offset = 0;
+    }
+
+    // Switches the post list to another story list, starting from the top
+    pub fn select_list(&mut self, filter: StoryListType) {
+        if let Page::PostList { .. } = self.page {
+            self.page = Page::PostList {
+                offset: 0,
+                cursor_index: 0,
+                filter,
+            };
+            self.scroll_offset = 0;
+        }
+    }
+
+    // Returns false if there is no page to go back to
+    pub fn back(&mut self) -> bool {
+        match self.history.pop() {
+            Some((page, scroll_offset)) => {
+                self.page = page;
+                self.scroll_offset = scroll_offset;
+                true
+            }
+            None => false,
         }
     }
 
-    pub fn scroll(&mut self, rows: u16, direction: ScrollDirection) {
+    // `item_count` is the number of rows the cursor can move through on the current page
+    pub fn scroll(&mut self, rows: u16, item_count: u32, direction: ScrollDirection) {
         match &self.page {
             Page::PostList {
                 cursor_index,
@@ -50,22 +155,8 @@ impl ViewState {
                 // Calculate number of posts that can be shown without overflow / crop
                 let number_of_posts = (rows - 1) / POST_ROW_SIZE as u16;
                 // adjust cursor position
-                let new_cursor: u32 = match direction {
-                    ScrollDirection::Up => {
-                        if cursor_index == &0 {
-                            0
-                        } else {
-                            cursor_index - 1
-                        }
-                    }
-                    ScrollDirection::Down => {
-                        if cursor_index == &((PAGE_SIZE - 1) as u32) {
-                            *cursor_index
-                        } else {
-                            cursor_index + 1
-                        }
-                    }
-                };
+                let max_index = (PAGE_SIZE as u32).min(item_count).saturating_sub(1);
+                let new_cursor = move_cursor(*cursor_index, max_index, &direction);
                 let scroll_offset = match direction {
                     ScrollDirection::Up => {
                         if self.scroll_offset as u32 > new_cursor {
@@ -86,17 +177,188 @@ impl ViewState {
                 self.page = Page::PostList {
                     offset: *offset,
                     cursor_index: new_cursor,
-                    filter: match filter {
-                        StoryListType::Top => StoryListType::Top,
-                        StoryListType::Best => StoryListType::Best,
-                        StoryListType::New => StoryListType::New,
-                    },
+                    filter: *filter,
                 };
                 self.scroll_offset = scroll_offset;
             }
-            Page::PostDetails { post, cursor_index } => {
-                // TODO - implement
+            Page::PostDetails { cursor_index, .. } | Page::CommentDetails { cursor_index, .. } => {
+                // Comments are rendered below the post or comment header with a fixed row size
+                let number_of_comments =
+                    rows.saturating_sub(self.details_header_size() + 1) / COMMENT_ROW_SIZE as u16;
+                let max_index = item_count.saturating_sub(1);
+                let new_cursor = move_cursor(*cursor_index, max_index, &direction);
+                let scroll_offset = if (new_cursor as u16) < self.scroll_offset {
+                    new_cursor as u16
+                } else if new_cursor as u16 >= self.scroll_offset + number_of_comments.max(1) {
+                    new_cursor as u16 + 1 - number_of_comments.max(1)
+                } else {
+                    self.scroll_offset
+                };
+
+                if let Page::PostDetails { cursor_index, .. }
+                | Page::CommentDetails { cursor_index, .. } = &mut self.page
+                {
+                    *cursor_index = new_cursor;
+                }
+                self.scroll_offset = scroll_offset;
+            }
+            Page::UserProfile {
+                user,
+                offset,
+                cursor_index,
+            } => {
+                // Submissions take a single row each below the user header
+                let number_of_submissions = rows.saturating_sub(USER_HEADER_SIZE as u16 + 1).max(1);
+                let max_index = item_count.saturating_sub(1);
+                let new_cursor = move_cursor(*cursor_index, max_index, &direction);
+                let scroll_offset = if (new_cursor as u16) < self.scroll_offset {
+                    new_cursor as u16
+                } else if new_cursor as u16 >= self.scroll_offset + number_of_submissions {
+                    new_cursor as u16 + 1 - number_of_submissions
+                } else {
+                    self.scroll_offset
+                };
+
+                self.page = Page::UserProfile {
+                    user: user.clone(),
+                    offset: *offset,
+                    cursor_index: new_cursor,
+                };
+                self.scroll_offset = scroll_offset;
             }
+            Page::JobDetails { .. } | Page::PollDetails { .. } | Page::OpenItem { .. } => {}
         };
     }
+
+    // Number of rows above the replies on a post or comment page
+    pub fn details_header_size(&self) -> u16 {
+        match self.page {
+            Page::CommentDetails { .. } => DETAILS_HEADER_SIZE as u16 + CONTEXT_SIZE as u16,
+            _ => DETAILS_HEADER_SIZE as u16,
+        }
+    }
+
+    // Moves between pages of PAGE_SIZE submissions on the user profile
+    // `total` is the number of submissions the user has
+    pub fn turn_page(&mut self, total: u32, direction: ScrollDirection) {
+        if let Page::UserProfile { user, offset, .. } = &self.page {
+            let new_offset = match direction {
+                ScrollDirection::Up => offset.saturating_sub(PAGE_SIZE as u32),
+                ScrollDirection::Down if offset + (PAGE_SIZE as u32) < total => {
+                    offset + PAGE_SIZE as u32
+                }
+                ScrollDirection::Down => *offset,
+            };
+            self.page = Page::UserProfile {
+                user: user.clone(),
+                offset: new_offset,
+                cursor_index: 0,
+            };
+            self.scroll_offset = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn navigate_and_back() {
+        let mut view_state = ViewState::init();
+        view_state.scroll(30, PAGE_SIZE as u32, ScrollDirection::Down);
+        view_state.navigate(Page::PostDetails {
+            post: 1,
+            cursor_index: 0,
+        });
+        assert!(matches!(view_state.page, Page::PostDetails { post: 1, .. }));
+
+        assert!(view_state.back());
+        match view_state.page {
+            Page::PostList { cursor_index, .. } => assert_eq!(cursor_index, 1),
+            _ => panic!("Unexpected Page variant"),
+        }
+        assert!(!view_state.back());
+    }
+
+    #[test]
+    fn post_details_scroll_bounds() {
+        let mut view_state = ViewState::init();
+        view_state.navigate(Page::PostDetails {
+            post: 1,
+            cursor_index: 0,
+        });
+        let rows = DETAILS_HEADER_SIZE as u16 + 1 + 2 * COMMENT_ROW_SIZE as u16;
+
+        view_state.scroll(rows, 3, ScrollDirection::Up);
+        for _ in 0..5 {
+            view_state.scroll(rows, 3, ScrollDirection::Down);
+        }
+        match view_state.page {
+            Page::PostDetails { cursor_index, .. } => assert_eq!(cursor_index, 2),
+            _ => panic!("Unexpected Page variant"),
+        }
+        // Two comments fit on screen, so the view scrolls by one to show the third
+        assert_eq!(view_state.scroll_offset, 1);
+    }
+
+    #[test]
+    fn opened_items_redirect() {
+        let mut view_state = ViewState::init();
+        view_state.navigate(Page::OpenItem { item: 2 });
+        view_state.redirect(Page::CommentDetails {
+            comment: 2,
+            cursor_index: 0,
+        });
+        view_state.scroll(30, 3, ScrollDirection::Down);
+        assert!(matches!(
+            view_state.page,
+            Page::CommentDetails {
+                comment: 2,
+                cursor_index: 1
+            }
+        ));
+
+        // The opened item is not kept in the history
+        assert!(view_state.back());
+        assert!(matches!(view_state.page, Page::PostList { .. }));
+    }
+
+    #[test]
+    fn user_profile_pages() {
+        let mut view_state = ViewState::init();
+        view_state.navigate(Page::UserProfile {
+            user: String::from("pg"),
+            offset: 0,
+            cursor_index: 0,
+        });
+        let total = PAGE_SIZE as u32 + 5;
+
+        view_state.scroll(30, PAGE_SIZE as u32, ScrollDirection::Down);
+        view_state.turn_page(total, ScrollDirection::Down);
+        match &view_state.page {
+            Page::UserProfile {
+                offset,
+                cursor_index,
+                ..
+            } => {
+                assert_eq!(*offset, PAGE_SIZE as u32);
+                assert_eq!(*cursor_index, 0);
+            }
+            _ => panic!("Unexpected Page variant"),
+        }
+
+        // There is no page past the last submission
+        view_state.turn_page(total, ScrollDirection::Down);
+        assert!(
+            matches!(view_state.page, Page::UserProfile { offset, .. } if offset == PAGE_SIZE as u32)
+        );
+
+        view_state.turn_page(total, ScrollDirection::Up);
+        view_state.turn_page(total, ScrollDirection::Up);
+        assert!(matches!(
+            view_state.page,
+            Page::UserProfile { offset: 0, .. }
+        ));
+    }
 }
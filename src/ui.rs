@@ -1,22 +1,46 @@
-use crate::constants::LEFT_OFFSET;
-use crate::stores::data::Post;
+use crate::constants::{
+    COMMENT_INDENT, COMMENT_ROW_SIZE, CONTEXT_SIZE, DETAILS_HEADER_SIZE, LEFT_OFFSET, MAX_LINKS,
+    PAGE_SIZE, POST_ROW_SIZE, USER_HEADER_SIZE,
+};
+use crate::stores::data::{Ancestor, Comment, Job, Poll, PollOption, Post, User};
+use crate::stores::view::StoryListType;
 use std::io::Write;
+use std::time::{Duration, Instant};
 
 use crossterm::{
     cursor,
     event::{self, Event, KeyCode, KeyEvent, MouseEvent},
     execute, queue,
-    style::{self, Print},
+    style::{self, Attribute, Color, Print, PrintStyledContent},
     terminal::{self, ClearType},
     Result as CrossTermResult,
 };
 
+mod html;
+
+pub use html::{decode_html, extract_links};
+use html::{render_html, Line, Style};
+
 pub enum UserAction {
     Up,
     Down,
     Enter,
-    // Refresh,
+    Back,
+    Refresh,
+    SelectList(StoryListType),
+    OpenUser,
+    NextPage,
+    PrevPage,
+    GoToItem,
+    // Opens the link with this number, starting from 1
+    OpenLink(usize),
+    // Opens the selected story's url, or its discussion if it has none
+    OpenUrl,
+    OpenDiscussion,
+    ToggleDebug,
     Rerender,
+    // No input before the timeout
+    Tick,
     Quit,
 }
 
@@ -54,27 +78,123 @@ where
     terminal::disable_raw_mode()
 }
 
-pub fn get_user_action() -> CrossTermResult<UserAction> {
+// Waits up to `timeout` for input, returning `Tick` if there was none
+pub fn get_user_action(timeout: Duration) -> CrossTermResult<UserAction> {
+    let deadline = Instant::now() + timeout;
     loop {
-        if let Ok(Event::Key(KeyEvent { code, .. })) = event::read() {
-            match code {
+        if !event::poll(deadline.saturating_duration_since(Instant::now()))? {
+            return Ok(UserAction::Tick);
+        }
+        match event::read()? {
+            Event::Key(KeyEvent { code, .. }) => match code {
                 KeyCode::Char('q') => return Ok(UserAction::Quit),
+                KeyCode::Char('r') => return Ok(UserAction::Refresh),
+                KeyCode::Char('t') => return Ok(UserAction::SelectList(StoryListType::Top)),
+                KeyCode::Char('n') => return Ok(UserAction::SelectList(StoryListType::New)),
+                KeyCode::Char('b') => return Ok(UserAction::SelectList(StoryListType::Best)),
+                KeyCode::Char('a') => return Ok(UserAction::SelectList(StoryListType::Ask)),
+                KeyCode::Char('s') => return Ok(UserAction::SelectList(StoryListType::Show)),
+                KeyCode::Char('j') => return Ok(UserAction::SelectList(StoryListType::Job)),
+                KeyCode::Char('u') => return Ok(UserAction::OpenUser),
+                KeyCode::Char('g') => return Ok(UserAction::GoToItem),
+                KeyCode::Char('d') => return Ok(UserAction::ToggleDebug),
+                KeyCode::Char('o') => return Ok(UserAction::OpenUrl),
+                KeyCode::Char('c') => return Ok(UserAction::OpenDiscussion),
+                KeyCode::Char(c @ '1'..='9') => {
+                    return Ok(UserAction::OpenLink(c as usize - '0' as usize))
+                }
+                KeyCode::PageDown => return Ok(UserAction::NextPage),
+                KeyCode::PageUp => return Ok(UserAction::PrevPage),
                 KeyCode::Esc => return Ok(UserAction::Quit),
                 KeyCode::Up => return Ok(UserAction::Up),
                 KeyCode::Down => return Ok(UserAction::Down),
                 KeyCode::Enter => return Ok(UserAction::Enter),
+                KeyCode::Backspace => return Ok(UserAction::Back),
+                KeyCode::Left => return Ok(UserAction::Back),
                 _ => continue,
-            }
-        } else if let Ok(Event::Mouse(mouse_event)) = event::read() {
-            match mouse_event {
+            },
+            Event::Mouse(mouse_event) => match mouse_event {
                 MouseEvent::ScrollUp(_, _, _) => return Ok(UserAction::Up),
                 MouseEvent::ScrollDown(_, _, _) => return Ok(UserAction::Down),
                 _ => continue,
+            },
+            Event::Resize(_, _) => return Ok(UserAction::Rerender),
+        }
+    }
+}
+
+// Reads a line of input typed on the last row after `prompt`
+// Returns None if the input is cancelled with Esc
+pub fn read_line<W>(w: &mut W, rows: u16, prompt: &str) -> CrossTermResult<Option<String>>
+where
+    W: Write,
+{
+    let mut input = String::new();
+    let line = loop {
+        queue!(
+            w,
+            cursor::MoveTo(0, rows.saturating_sub(1)),
+            terminal::Clear(ClearType::CurrentLine),
+            cursor::MoveRight(LEFT_OFFSET),
+            Print(prompt),
+            Print(&input),
+            cursor::Show
+        )?;
+        w.flush()?;
+        if let Event::Key(KeyEvent { code, .. }) = event::read()? {
+            match code {
+                KeyCode::Enter => break Some(input),
+                KeyCode::Esc => break None,
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                KeyCode::Char(c) => input.push(c),
+                _ => {}
             }
-        } else if let Ok(Event::Resize(_, _)) = event::read() {
-            return Ok(UserAction::Rerender);
+        }
+    };
+    queue!(w, cursor::Hide)?;
+    Ok(line)
+}
+
+// Prints the story lists on the last row, with the selected list highlighted
+pub fn print_list_footer<W>(w: &mut W, rows: u16, selected: &StoryListType) -> CrossTermResult<()>
+where
+    W: Write,
+{
+    queue!(
+        w,
+        cursor::MoveTo(0, rows.saturating_sub(1)),
+        terminal::Clear(ClearType::CurrentLine),
+        cursor::MoveRight(LEFT_OFFSET)
+    )?;
+    for story_type in StoryListType::ALL.iter() {
+        let label = story_type.label();
+        // The first letter of the label selects the list
+        let text = format!("[{}]{} ", &label[..1], &label[1..]);
+        if story_type == selected {
+            queue!(
+                w,
+                style::SetAttribute(style::Attribute::Reverse),
+                Print(text),
+                style::SetAttribute(style::Attribute::Reset)
+            )?;
+        } else {
+            queue!(w, Print(text))?;
         }
     }
+    Ok(())
+}
+
+// The title of a story or poll, deleted ones have no title left to show
+pub fn story_title(title: &str, dead: bool, deleted: bool) -> String {
+    if deleted {
+        String::from("[deleted]")
+    } else if dead {
+        format!("[dead] {}", title)
+    } else {
+        title.to_string()
+    }
 }
 
 pub fn print_post<W>(w: &mut W, number: usize, columns: u16, post: &Post) -> CrossTermResult<()>
@@ -82,18 +202,430 @@ where
     W: Write,
 {
     // Posts will take up exactly 3 rows - things will be cropped otherwise
-    // TODO - handle size constraints - crop post title if long
-    let main_line = format!(
+    let width = columns.saturating_sub(LEFT_OFFSET) as usize;
+    let main_line: String = format!(
         "{number} - {post_title}",
         number = number + 1,
-        post_title = post.title,
-    );
+        post_title = story_title(&post.title, post.dead, post.deleted),
+    )
+    .chars()
+    .take(width)
+    .collect();
+    let sub_line = if post.deleted {
+        String::new()
+    } else {
+        format!(
+            "{score} points - {post_author} - {time} - {descendants} comments",
+            score = post.score,
+            post_author = post.by,
+            time = post.time,
+            descendants = post.descendants
+        )
+    };
+    queue!(w, Print(main_line), cursor::MoveToNextLine(1),)?;
+    queue!(
+        w,
+        cursor::MoveRight(LEFT_OFFSET + 4),
+        Print(sub_line),
+        cursor::MoveToNextLine(1),
+    )?;
+    queue!(w, cursor::MoveRight(LEFT_OFFSET), cursor::MoveToNextLine(1))?;
+
+    Ok(())
+}
+
+// Prints a line rendered from HTML, cropped to `width` columns
+fn print_line<W>(w: &mut W, line: &Line, width: usize) -> CrossTermResult<()>
+where
+    W: Write,
+{
+    if line.indent > 0 {
+        queue!(w, cursor::MoveRight(line.indent as u16))?;
+    }
+    let mut remaining = width.saturating_sub(line.indent);
+    for span in &line.spans {
+        let text: String = span.text.chars().take(remaining).collect();
+        remaining -= text.chars().count();
+        let styled = style::style(text);
+        match span.style {
+            Style::Plain => queue!(w, PrintStyledContent(styled))?,
+            Style::Italic => queue!(w, PrintStyledContent(styled.attribute(Attribute::Italic)))?,
+            Style::Code => queue!(w, PrintStyledContent(styled.with(Color::Green)))?,
+            Style::Quote => queue!(w, PrintStyledContent(styled.with(Color::DarkGrey)))?,
+            Style::Link(_) => queue!(
+                w,
+                PrintStyledContent(styled.attribute(Attribute::Underlined))
+            )?,
+        }
+    }
+
+    Ok(())
+}
+
+pub fn print_post_header<W>(w: &mut W, columns: u16, post: &Post) -> CrossTermResult<()>
+where
+    W: Write,
+{
+    // The header takes up exactly DETAILS_HEADER_SIZE rows:
+    // title, sub line, url, text (cropped) and a blank separator
+    let width = columns.saturating_sub(LEFT_OFFSET) as usize;
     let sub_line = format!(
-        "{post_author} - {time} - {descendants} comments",
+        "{score} points - {post_author} - {time} - {descendants} comments",
+        score = post.score,
         post_author = post.by,
         time = post.time,
         descendants = post.descendants
     );
+    let title = story_title(&post.title, post.dead, post.deleted);
+    let mut lines = vec![Line::plain(title), Line::plain(sub_line)];
+    if let Some(url) = &post.url {
+        lines.push(Line::plain(url.clone()));
+    }
+    if let Some(text) = &post.text {
+        lines.extend(render_html(text, width, 0).lines);
+    }
+    lines.resize(DETAILS_HEADER_SIZE as usize - 1, Line::default());
+
+    for line in lines {
+        queue!(w, cursor::MoveRight(LEFT_OFFSET))?;
+        print_line(w, &line, width)?;
+        queue!(w, cursor::MoveToNextLine(1))?;
+    }
+    queue!(w, cursor::MoveToNextLine(1))?;
+
+    Ok(())
+}
+
+// One line summary of a comment, e.g. for the parents shown above a comment
+fn summarize_comment(comment: &Comment) -> String {
+    match (&comment.by, &comment.text) {
+        (Some(by), Some(text)) if !comment.deleted && !comment.dead => {
+            let text = decode_html(text);
+            format!(
+                "{}: {}",
+                by,
+                text.split_whitespace().collect::<Vec<&str>>().join(" ")
+            )
+        }
+        _ => String::from("[deleted]"),
+    }
+}
+
+// The parents of a comment, a line each from the root down, with `status` first
+// Long chains are collapsed to the root and the nearest parents to fit `max_lines`
+fn context_lines(ancestors: &[Ancestor], status: Option<&str>, max_lines: usize) -> Vec<String> {
+    let mut lines: Vec<String> = ancestors
+        .iter()
+        .enumerate()
+        .map(|(depth, ancestor)| {
+            let summary = match ancestor {
+                // Deleted stories have no author to show
+                Ancestor::Post(post) if post.deleted => story_title(&post.title, false, true),
+                Ancestor::Post(post) => format!(
+                    "{} - {}",
+                    story_title(&post.title, post.dead, false),
+                    post.by
+                ),
+                Ancestor::Poll(poll) if poll.deleted => {
+                    format!("[poll] {}", story_title(&poll.title, false, true))
+                }
+                Ancestor::Poll(poll) => format!(
+                    "[poll] {} - {}",
+                    story_title(&poll.title, poll.dead, false),
+                    poll.by
+                ),
+                Ancestor::Comment(comment) => summarize_comment(comment),
+            };
+            format!(
+                "{indent}{summary}",
+                indent = " ".repeat(depth * COMMENT_INDENT as usize),
+                summary = summary
+            )
+        })
+        .collect();
+    // The status takes one of the lines, the root is always kept below it
+    let max_lines = max_lines.saturating_sub(status.map_or(0, |_| 1)).max(3);
+    if lines.len() > max_lines {
+        let hidden = lines.len() - (max_lines - 1);
+        let nearest = lines.split_off(lines.len() - (max_lines - 2));
+        lines.truncate(1);
+        lines.push(format!("... {} more", hidden));
+        lines.extend(nearest);
+    }
+    if let Some(status) = status {
+        lines.insert(0, status.to_string());
+    }
+    lines
+}
+
+// Prints the parents of a comment above it
+// `status` is shown first while the parents are loading or if they failed to load
+pub fn print_context<W>(
+    w: &mut W,
+    columns: u16,
+    ancestors: &[Ancestor],
+    status: Option<&str>,
+) -> CrossTermResult<()>
+where
+    W: Write,
+{
+    // The context takes up exactly CONTEXT_SIZE rows - the parents and a blank separator
+    let width = columns.saturating_sub(LEFT_OFFSET) as usize;
+    let max_lines = CONTEXT_SIZE as usize - 1;
+    let mut lines = context_lines(ancestors, status, max_lines);
+    lines.resize(max_lines, String::new());
+
+    for line in lines {
+        let line: String = line.chars().take(width).collect();
+        queue!(
+            w,
+            cursor::MoveRight(LEFT_OFFSET),
+            Print(line),
+            cursor::MoveToNextLine(1)
+        )?;
+    }
+    queue!(w, cursor::MoveToNextLine(1))?;
+
+    Ok(())
+}
+
+// The header of a comment's page, above its replies
+pub fn print_comment_header<W>(w: &mut W, columns: u16, comment: &Comment) -> CrossTermResult<()>
+where
+    W: Write,
+{
+    // The header takes up exactly DETAILS_HEADER_SIZE rows like the post header:
+    // author line, text (cropped) and a blank separator
+    let width = columns.saturating_sub(LEFT_OFFSET) as usize;
+    let sub_line = format!(
+        "{comment_author} - {time} - {replies} replies",
+        comment_author = comment.by.as_deref().unwrap_or("[deleted]"),
+        time = comment.time,
+        replies = comment.children.len()
+    );
+    let mut lines = vec![Line::plain(sub_line)];
+    if !comment.deleted && !comment.dead {
+        if let Some(text) = &comment.text {
+            lines.extend(render_html(text, width, 0).lines);
+        }
+    }
+    lines.resize(DETAILS_HEADER_SIZE as usize - 1, Line::default());
+
+    for line in lines {
+        queue!(w, cursor::MoveRight(LEFT_OFFSET))?;
+        print_line(w, &line, width)?;
+        queue!(w, cursor::MoveToNextLine(1))?;
+    }
+    queue!(w, cursor::MoveToNextLine(1))?;
+
+    Ok(())
+}
+
+pub fn print_comment<W>(
+    w: &mut W,
+    depth: usize,
+    columns: u16,
+    comment: &Comment,
+) -> CrossTermResult<()>
+where
+    W: Write,
+{
+    // Comments take up exactly COMMENT_ROW_SIZE rows - a header line, the text
+    // cropped to fit and a blank line
+    let indent = depth as u16 * COMMENT_INDENT;
+    let width = columns.saturating_sub(LEFT_OFFSET + indent) as usize;
+    // Deleted and dead comments are shown as placeholders so their replies keep their place
+    let header = if comment.deleted {
+        format!("[deleted] - {} replies", comment.children.len())
+    } else if comment.dead {
+        format!("[dead] - {} replies", comment.children.len())
+    } else {
+        format!(
+            "{comment_author} - {time} - {replies} replies",
+            comment_author = comment.by.as_deref().unwrap_or("unknown"),
+            time = comment.time,
+            replies = comment.children.len()
+        )
+    };
+    let mut lines = vec![Line::plain(header)];
+    if !comment.deleted && !comment.dead {
+        if let Some(text) = &comment.text {
+            lines.extend(
+                render_html(text, width, 0)
+                    .lines
+                    .into_iter()
+                    .take(COMMENT_ROW_SIZE as usize - 2),
+            );
+        }
+    }
+    lines.resize(COMMENT_ROW_SIZE as usize - 1, Line::default());
+
+    for (i, line) in lines.into_iter().enumerate() {
+        // The first line is printed after the cursor marker
+        if i > 0 {
+            queue!(w, cursor::MoveRight(LEFT_OFFSET))?;
+        }
+        if indent > 0 {
+            queue!(w, cursor::MoveRight(indent))?;
+        }
+        print_line(w, &line, width)?;
+        queue!(w, cursor::MoveToNextLine(1))?;
+    }
+    queue!(w, cursor::MoveToNextLine(1))?;
+
+    Ok(())
+}
+
+// Placeholders take up the same number of rows as a comment so scrolling stays uniform
+fn print_comment_placeholder<W>(
+    w: &mut W,
+    depth: usize,
+    columns: u16,
+    text: &str,
+) -> CrossTermResult<()>
+where
+    W: Write,
+{
+    let indent = depth as u16 * COMMENT_INDENT;
+    let width = columns.saturating_sub(LEFT_OFFSET + indent) as usize;
+    if indent > 0 {
+        queue!(w, cursor::MoveRight(indent))?;
+    }
+    let text: String = text.chars().take(width).collect();
+    queue!(
+        w,
+        Print(text),
+        cursor::MoveToNextLine(COMMENT_ROW_SIZE as u16)
+    )
+}
+
+pub fn print_more_replies<W>(
+    w: &mut W,
+    depth: usize,
+    columns: u16,
+    count: usize,
+) -> CrossTermResult<()>
+where
+    W: Write,
+{
+    let text = if count == 1 {
+        String::from("1 more reply")
+    } else {
+        format!("{} more replies", count)
+    };
+    print_comment_placeholder(w, depth, columns, &text)
+}
+
+pub fn print_failed_comment<W>(
+    w: &mut W,
+    depth: usize,
+    columns: u16,
+    id: u32,
+    error: &str,
+) -> CrossTermResult<()>
+where
+    W: Write,
+{
+    let text = format!(
+        "[comment {} failed to load: {}] - enter to retry",
+        id, error
+    );
+    print_comment_placeholder(w, depth, columns, &text)
+}
+
+pub fn print_job<W>(w: &mut W, number: usize, columns: u16, job: &Job) -> CrossTermResult<()>
+where
+    W: Write,
+{
+    // Jobs take up the same 3 rows as a post
+    let width = columns.saturating_sub(LEFT_OFFSET) as usize;
+    let main_line: String = format!(
+        "{number} - [job] {job_title}",
+        number = number + 1,
+        job_title = story_title(&job.title, job.dead, job.deleted),
+    )
+    .chars()
+    .take(width)
+    .collect();
+    let sub_line = if job.deleted {
+        String::new()
+    } else {
+        format!(
+            "{job_author} - {time}",
+            job_author = job.by,
+            time = job.time
+        )
+    };
+    queue!(w, Print(main_line), cursor::MoveToNextLine(1),)?;
+    queue!(
+        w,
+        cursor::MoveRight(LEFT_OFFSET + 4),
+        Print(sub_line),
+        cursor::MoveToNextLine(1),
+    )?;
+    queue!(w, cursor::MoveRight(LEFT_OFFSET), cursor::MoveToNextLine(1))?;
+
+    Ok(())
+}
+
+pub fn print_job_details<W>(w: &mut W, columns: u16, rows: u16, job: &Job) -> CrossTermResult<()>
+where
+    W: Write,
+{
+    let width = columns.saturating_sub(LEFT_OFFSET) as usize;
+    let mut lines = vec![
+        Line::plain(format!(
+            "[job] {}",
+            story_title(&job.title, job.dead, job.deleted)
+        )),
+        Line::plain(format!(
+            "{job_author} - {time}",
+            job_author = job.by,
+            time = job.time
+        )),
+    ];
+    if let Some(url) = &job.url {
+        lines.push(Line::plain(format!("apply: {}", url)));
+    }
+    lines.push(Line::default());
+    if let Some(text) = &job.text {
+        lines.extend(render_html(text, width, 0).lines);
+    }
+
+    for line in lines.into_iter().take(rows.saturating_sub(1) as usize) {
+        queue!(w, cursor::MoveRight(LEFT_OFFSET))?;
+        print_line(w, &line, width)?;
+        queue!(w, cursor::MoveToNextLine(1))?;
+    }
+
+    Ok(())
+}
+
+pub fn print_poll<W>(w: &mut W, number: usize, columns: u16, poll: &Poll) -> CrossTermResult<()>
+where
+    W: Write,
+{
+    // Polls take up the same 3 rows as a post
+    let width = columns.saturating_sub(LEFT_OFFSET) as usize;
+    let main_line: String = format!(
+        "{number} - [poll] {poll_title}",
+        number = number + 1,
+        poll_title = story_title(&poll.title, poll.dead, poll.deleted),
+    )
+    .chars()
+    .take(width)
+    .collect();
+    let sub_line = if poll.deleted {
+        String::new()
+    } else {
+        format!(
+            "{score} points - {poll_author} - {time} - {descendants} comments",
+            score = poll.score,
+            poll_author = poll.by,
+            time = poll.time,
+            descendants = poll.descendants
+        )
+    };
     queue!(w, Print(main_line), cursor::MoveToNextLine(1),)?;
     queue!(
         w,
@@ -105,3 +637,363 @@ where
 
     Ok(())
 }
+
+// A bar `width` characters wide when `score` is the highest score of the poll
+pub fn poll_bar(score: u16, max_score: u16, width: usize) -> String {
+    let length = if max_score == 0 {
+        0
+    } else {
+        (score as usize * width + max_score as usize / 2) / max_score as usize
+    };
+    "█".repeat(length.min(width))
+}
+
+pub fn print_poll_details<W>(
+    w: &mut W,
+    columns: u16,
+    rows: u16,
+    poll: &Poll,
+    options: &[&PollOption],
+) -> CrossTermResult<()>
+where
+    W: Write,
+{
+    let width = columns.saturating_sub(LEFT_OFFSET) as usize;
+    let mut lines = vec![
+        Line::plain(format!(
+            "[poll] {}",
+            story_title(&poll.title, poll.dead, poll.deleted)
+        )),
+        Line::plain(format!(
+            "{poll_author} - {time} - {score} points",
+            poll_author = poll.by,
+            time = poll.time,
+            score = poll.score
+        )),
+    ];
+    if let Some(text) = &poll.text {
+        lines.extend(render_html(text, width, 0).lines);
+    }
+    lines.push(Line::default());
+
+    // Each option is shown as its text followed by a bar of its votes
+    let max_score = options.iter().map(|option| option.score).max().unwrap_or(0);
+    let bar_width = width.saturating_sub(12);
+    for option in options {
+        lines.push(Line::plain(decode_html(&option.text)));
+        lines.push(Line::plain(format!(
+            "{bar} {score} votes",
+            bar = poll_bar(option.score, max_score, bar_width),
+            score = option.score
+        )));
+    }
+    if options.len() < poll.parts.len() {
+        lines.push(Line::plain(format!(
+            "{} options not loaded - r to retry",
+            poll.parts.len() - options.len()
+        )));
+    }
+
+    for line in lines.into_iter().take(rows.saturating_sub(1) as usize) {
+        queue!(w, cursor::MoveRight(LEFT_OFFSET))?;
+        print_line(w, &line, width)?;
+        queue!(w, cursor::MoveToNextLine(1))?;
+    }
+
+    Ok(())
+}
+
+// Shown in place of a post that is not loaded, e.g. if the request failed
+pub fn print_post_placeholder<W>(
+    w: &mut W,
+    number: usize,
+    columns: u16,
+    text: &str,
+) -> CrossTermResult<()>
+where
+    W: Write,
+{
+    let width = columns.saturating_sub(LEFT_OFFSET) as usize;
+    let main_line: String = format!("{number} - {text}", number = number + 1, text = text)
+        .chars()
+        .take(width)
+        .collect();
+    queue!(
+        w,
+        Print(main_line),
+        cursor::MoveToNextLine(POST_ROW_SIZE as u16)
+    )
+}
+
+// How long ago `created` was, in the largest whole unit
+pub fn format_age(created: u32, now: u64) -> String {
+    let days = now.saturating_sub(created as u64) / (60 * 60 * 24);
+    let (count, unit) = if days >= 365 {
+        (days / 365, "year")
+    } else if days >= 30 {
+        (days / 30, "month")
+    } else {
+        (days, "day")
+    };
+    if count == 1 {
+        format!("1 {}", unit)
+    } else {
+        format!("{} {}s", count, unit)
+    }
+}
+
+pub fn print_user_header<W>(w: &mut W, columns: u16, user: &User, now: u64) -> CrossTermResult<()>
+where
+    W: Write,
+{
+    // The header takes up exactly USER_HEADER_SIZE rows:
+    // username, karma and age, about (cropped) and a blank separator
+    let width = columns.saturating_sub(LEFT_OFFSET) as usize;
+    let mut lines = vec![
+        Line::plain(user.id.clone()),
+        Line::plain(format!(
+            "{karma} karma - joined {age} ago - {submissions} submissions",
+            karma = user.karma,
+            age = format_age(user.created, now),
+            submissions = user.submitted.len()
+        )),
+    ];
+    if let Some(about) = &user.about {
+        lines.extend(render_html(about, width, 0).lines);
+    }
+    lines.resize(USER_HEADER_SIZE as usize - 1, Line::default());
+
+    for line in lines {
+        queue!(w, cursor::MoveRight(LEFT_OFFSET))?;
+        print_line(w, &line, width)?;
+        queue!(w, cursor::MoveToNextLine(1))?;
+    }
+    queue!(w, cursor::MoveToNextLine(1))?;
+
+    Ok(())
+}
+
+// Submissions take up a single row
+pub fn print_submission<W>(
+    w: &mut W,
+    number: usize,
+    columns: u16,
+    text: &str,
+) -> CrossTermResult<()>
+where
+    W: Write,
+{
+    let width = columns.saturating_sub(LEFT_OFFSET) as usize;
+    let line: String = format!("{number} - {text}", number = number + 1, text = text)
+        .chars()
+        .take(width)
+        .collect();
+    queue!(w, Print(line), cursor::MoveToNextLine(1))
+}
+
+// Prints the range of submissions shown on the last row
+pub fn print_user_footer<W>(w: &mut W, rows: u16, offset: u32, total: usize) -> CrossTermResult<()>
+where
+    W: Write,
+{
+    let end = (offset as usize + PAGE_SIZE as usize).min(total);
+    let text = format!(
+        "submissions {start}-{end} of {total} - [pgup/pgdn] page",
+        start = (offset as usize + 1).min(end),
+        end = end,
+        total = total
+    );
+    queue!(
+        w,
+        cursor::MoveTo(0, rows.saturating_sub(1)),
+        terminal::Clear(ClearType::CurrentLine),
+        cursor::MoveRight(LEFT_OFFSET),
+        Print(text)
+    )
+}
+
+// A link without its scheme, e.g. `example.com/a` for `https://example.com/a`
+fn link_label(link: &str) -> &str {
+    link.split("://")
+        .nth(1)
+        .unwrap_or(link)
+        .trim_start_matches("www.")
+}
+
+// Prints the numbered links of the focused text on the last row, cropped to fit
+// Only the first MAX_LINKS are listed as they are opened with a single key
+pub fn print_links_footer<W>(
+    w: &mut W,
+    columns: u16,
+    rows: u16,
+    links: &[String],
+) -> CrossTermResult<()>
+where
+    W: Write,
+{
+    if links.is_empty() {
+        return Ok(());
+    }
+    let width = columns.saturating_sub(LEFT_OFFSET) as usize;
+    let text = links
+        .iter()
+        .take(MAX_LINKS)
+        .enumerate()
+        .map(|(i, link)| format!("[{}] {}", i + 1, link_label(link)))
+        .collect::<Vec<_>>()
+        .join("  ");
+    let text: String = text.chars().take(width).collect();
+    queue!(
+        w,
+        cursor::MoveTo(0, rows.saturating_sub(1)),
+        terminal::Clear(ClearType::CurrentLine),
+        cursor::MoveRight(LEFT_OFFSET),
+        Print(text)
+    )
+}
+
+// Prints `lines` over the top right corner of the screen
+pub fn print_debug_overlay<W>(w: &mut W, columns: u16, lines: &[String]) -> CrossTermResult<()>
+where
+    W: Write,
+{
+    for (row, line) in lines.iter().enumerate() {
+        let width = line.chars().count() as u16;
+        queue!(
+            w,
+            cursor::MoveTo(columns.saturating_sub(width + LEFT_OFFSET), row as u16),
+            style::SetAttribute(style::Attribute::Reverse),
+            Print(line),
+            style::SetAttribute(style::Attribute::Reset)
+        )?;
+    }
+    w.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn poll_bar_is_proportional_to_max_score() {
+        assert_eq!(poll_bar(10, 10, 4), "████");
+        assert_eq!(poll_bar(5, 10, 4), "██");
+        assert_eq!(poll_bar(0, 10, 4), "");
+        assert_eq!(poll_bar(0, 0, 4), "");
+    }
+
+    fn make_comment(id: u32, by: &str, text: &str) -> Comment {
+        Comment {
+            id,
+            by: Some(by.to_string()),
+            children: vec![id + 1],
+            parent: id - 1,
+            text: Some(text.to_string()),
+            time: 0,
+            dead: false,
+            deleted: false,
+        }
+    }
+
+    #[test]
+    fn context_collapses_long_chains() {
+        let post = Post {
+            id: 1,
+            by: String::from("pg"),
+            children: vec![2],
+            title: String::from("Launch"),
+            time: 0,
+            url: None,
+            text: None,
+            descendants: 4,
+            score: 1,
+            dead: false,
+            deleted: false,
+        };
+        let comments: Vec<Comment> = (2..6)
+            .map(|id| make_comment(id, "sama", &format!("<p>reply &amp; {}", id)))
+            .collect();
+        let mut ancestors = vec![Ancestor::Post(&post)];
+        ancestors.extend(comments.iter().map(Ancestor::Comment));
+
+        let lines = context_lines(&ancestors[..2], None, 4);
+        assert_eq!(lines, vec!["Launch - pg", "  sama: reply & 2"]);
+
+        let lines = context_lines(&ancestors, None, 4);
+        assert_eq!(
+            lines,
+            vec![
+                "Launch - pg",
+                "... 2 more",
+                "      sama: reply & 4",
+                "        sama: reply & 5"
+            ]
+        );
+
+        // The status is shown above the root rather than in place of it
+        let lines = context_lines(&ancestors, Some("Loading context..."), 4);
+        assert_eq!(
+            lines,
+            vec![
+                "Loading context...",
+                "Launch - pg",
+                "... 3 more",
+                "        sama: reply & 5"
+            ]
+        );
+    }
+
+    #[test]
+    fn context_shows_deleted_roots() {
+        let post = Post {
+            id: 1,
+            by: String::new(),
+            children: vec![2],
+            title: String::new(),
+            time: 0,
+            url: None,
+            text: None,
+            descendants: 1,
+            score: 0,
+            dead: false,
+            deleted: true,
+        };
+        let comment = make_comment(2, "sama", "reply");
+        let ancestors = vec![Ancestor::Post(&post), Ancestor::Comment(&comment)];
+
+        let lines = context_lines(&ancestors, None, 4);
+        assert_eq!(lines, vec!["[deleted]", "  sama: reply"]);
+    }
+
+    #[test]
+    fn deleted_stories_have_a_placeholder_title() {
+        assert_eq!(story_title("", false, true), "[deleted]");
+        assert_eq!(story_title("Show HN: a", true, false), "[dead] Show HN: a");
+        assert_eq!(story_title("Show HN: a", false, false), "Show HN: a");
+    }
+
+    #[test]
+    fn link_labels_drop_the_scheme() {
+        assert_eq!(
+            link_label("https://www.example.com/a?b=1"),
+            "example.com/a?b=1"
+        );
+        assert_eq!(
+            link_label("https://news.ycombinator.com/item?id=1"),
+            "news.ycombinator.com/item?id=1"
+        );
+        assert_eq!(link_label("mailto:pg@example.com"), "mailto:pg@example.com");
+    }
+
+    #[test]
+    fn format_age_uses_largest_unit() {
+        let day = 60 * 60 * 24;
+        assert_eq!(format_age(0, 0), "0 days");
+        assert_eq!(format_age(0, day), "1 day");
+        assert_eq!(format_age(0, 45 * day), "1 month");
+        assert_eq!(format_age(0, 800 * day), "2 years");
+        // Clocks can disagree, future times are treated as now
+        assert_eq!(format_age(day as u32, 0), "0 days");
+    }
+}
@@ -0,0 +1,489 @@
+// Turns the HTML the API returns for comments, stories and profiles into styled lines
+// for the terminal. HN only uses a handful of tags: <p> before each paragraph after the
+// first, <i>, <a href> and <pre><code> around code, with text escaped as entities
+// e.g. &#x27; and &quot;
+use std::iter::Peekable;
+use std::str::Chars;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Style {
+    Plain,
+    Italic,
+    // Preformatted text, kept as is rather than wrapped
+    Code,
+    // Paragraphs starting with `>`, the convention for quoting on HN
+    Quote,
+    // Part of the link numbered `n` in `extract_links`, starting from 1
+    Link(usize),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Span {
+    pub text: String,
+    pub style: Style,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Line {
+    // Number of columns before the first span
+    pub indent: usize,
+    pub spans: Vec<Span>,
+}
+
+pub struct Rendered {
+    pub lines: Vec<Line>,
+}
+
+enum Block {
+    Paragraph(Vec<Span>),
+    Code(String),
+}
+
+impl Line {
+    pub fn plain(text: String) -> Line {
+        Line {
+            indent: 0,
+            spans: vec![Span {
+                text,
+                style: Style::Plain,
+            }],
+        }
+    }
+
+    // The text of the line without styles or indent
+    #[cfg(test)]
+    pub fn text(&self) -> String {
+        self.spans.iter().map(|span| span.text.as_str()).collect()
+    }
+
+    // Number of characters in the line, not counting the indent
+    fn len(&self) -> usize {
+        self.spans
+            .iter()
+            .map(|span| span.text.chars().count())
+            .sum()
+    }
+
+    fn last_style(&self) -> Option<Style> {
+        self.spans.last().map(|span| span.style)
+    }
+
+    fn push(&mut self, c: char, style: Style) {
+        match self.spans.last_mut() {
+            Some(span) if span.style == style => span.text.push(c),
+            _ => self.spans.push(Span {
+                text: c.to_string(),
+                style,
+            }),
+        }
+    }
+}
+
+// Reads up to the end of a tag, after its `<`
+fn read_tag(chars: &mut Peekable<Chars>) -> String {
+    let mut tag = String::new();
+    for c in chars.by_ref() {
+        if c == '>' {
+            break;
+        }
+        tag.push(c);
+    }
+    tag
+}
+
+// Decodes a character reference after its `&`, e.g. `amp;` or `#x27;`
+// Text that is not a reference is returned as is, e.g. the `&` in `R&D`, and only
+// characters that can be part of a reference are read so tags after it are kept
+fn read_reference(chars: &mut Peekable<Chars>) -> String {
+    let mut reference = String::new();
+    while let Some(&c) = chars.peek() {
+        if !(c.is_ascii_alphanumeric() || c == '#') || reference.len() > 8 {
+            break;
+        }
+        reference.push(c);
+        chars.next();
+    }
+    let decoded = match reference.as_str() {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        "nbsp" => Some(' '),
+        _ if reference.starts_with("#x") || reference.starts_with("#X") => {
+            u32::from_str_radix(&reference[2..], 16)
+                .ok()
+                .and_then(std::char::from_u32)
+        }
+        _ if reference.starts_with('#') => {
+            reference[1..].parse().ok().and_then(std::char::from_u32)
+        }
+        _ => None,
+    };
+    match (decoded, chars.peek()) {
+        (Some(decoded), Some(';')) => {
+            chars.next();
+            decoded.to_string()
+        }
+        _ => format!("&{}", reference),
+    }
+}
+
+// The decoded value of an attribute in a tag, e.g. `href` in `a href="..."`
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    let start = tag.find(&format!("{}=\"", name))? + name.len() + 2;
+    let end = start + tag[start..].find('"')?;
+    Some(decode_html(&tag[start..end]))
+}
+
+// Links relative to HN, e.g. `item?id=1`, are made absolute so they can be opened
+fn resolve_link(href: &str) -> String {
+    if href.contains("://") || href.is_empty() {
+        href.to_string()
+    } else {
+        format!(
+            "https://news.ycombinator.com/{}",
+            href.trim_start_matches('/')
+        )
+    }
+}
+
+// The targets of the links in `html`, in the order `render_html` numbers them
+pub fn extract_links(html: &str) -> Vec<String> {
+    parse(html).1
+}
+
+// Decodes the text of `html`, tags are dropped and paragraphs are separated by a blank line
+pub fn decode_html(html: &str) -> String {
+    let mut text = String::new();
+    let mut chars = html.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '<' => {
+                if read_tag(&mut chars).eq_ignore_ascii_case("p") {
+                    text.push_str("\n\n");
+                }
+            }
+            '&' => text.push_str(&read_reference(&mut chars)),
+            _ => text.push(c),
+        }
+    }
+    text
+}
+
+fn parse(html: &str) -> (Vec<Block>, Vec<String>) {
+    fn finish_paragraph(blocks: &mut Vec<Block>, spans: &mut Vec<Span>) {
+        if spans.iter().any(|span| !span.text.trim().is_empty()) {
+            blocks.push(Block::Paragraph(std::mem::take(spans)));
+        }
+        spans.clear();
+    }
+
+    let mut blocks = Vec::new();
+    let mut links = Vec::new();
+    let mut spans: Vec<Span> = Vec::new();
+    // Text inside <pre>
+    let mut code: Option<String> = None;
+    let mut italic = false;
+    let mut link: Option<usize> = None;
+    let mut chars = html.chars().peekable();
+    while let Some(c) = chars.next() {
+        let text = match c {
+            '<' => {
+                let tag = read_tag(&mut chars);
+                let name = tag.split_whitespace().next().unwrap_or("").to_lowercase();
+                match name.as_str() {
+                    "p" => finish_paragraph(&mut blocks, &mut spans),
+                    "i" => italic = true,
+                    "/i" => italic = false,
+                    "a" => {
+                        let href = attribute(&tag, "href").unwrap_or_default();
+                        links.push(resolve_link(&href));
+                        link = Some(links.len());
+                    }
+                    "/a" => {
+                        if let Some(number) = link.take() {
+                            spans.push(Span {
+                                text: format!(" [{}]", number),
+                                style: Style::Link(number),
+                            });
+                        }
+                    }
+                    "pre" => {
+                        finish_paragraph(&mut blocks, &mut spans);
+                        code = Some(String::new());
+                    }
+                    "/pre" => {
+                        if let Some(code) = code.take() {
+                            blocks.push(Block::Code(code));
+                        }
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+            '&' => read_reference(&mut chars),
+            c => c.to_string(),
+        };
+        if let Some(code) = &mut code {
+            code.push_str(&text);
+            continue;
+        }
+        let style = match (link, italic) {
+            (Some(number), _) => Style::Link(number),
+            (None, true) => Style::Italic,
+            (None, false) => Style::Plain,
+        };
+        match spans.last_mut() {
+            Some(span) if span.style == style => span.text.push_str(&text),
+            _ => spans.push(Span { text, style }),
+        }
+    }
+    if let Some(code) = code {
+        blocks.push(Block::Code(code));
+    }
+    finish_paragraph(&mut blocks, &mut spans);
+    (blocks, links)
+}
+
+// Fills lines up to `width` characters with the words of a paragraph
+// Words longer than `width` are split across lines
+fn wrap_spans(spans: &[Span], width: usize) -> Vec<Line> {
+    // A word is a run of characters without whitespace, which can change style part way
+    let mut words: Vec<Vec<(char, Style)>> = Vec::new();
+    let mut word = Vec::new();
+    for span in spans {
+        for c in span.text.chars() {
+            if c.is_whitespace() {
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+            } else {
+                word.push((c, span.style));
+            }
+        }
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+
+    let mut lines = Vec::new();
+    let mut line = Line::default();
+    for word in words {
+        let line_len = line.len();
+        if line_len > 0 && line_len + 1 + word.len() > width {
+            lines.push(std::mem::take(&mut line));
+        }
+        if line.len() > 0 {
+            // Spaces inside italics or a link keep the style
+            let space_style = match line.last_style() {
+                Some(style) if style == word[0].1 => style,
+                _ => Style::Plain,
+            };
+            line.push(' ', space_style);
+        }
+        for (c, style) in word {
+            if line.len() >= width {
+                lines.push(std::mem::take(&mut line));
+            }
+            line.push(c, style);
+        }
+    }
+    if line.len() > 0 {
+        lines.push(line);
+    }
+    lines
+}
+
+// Code keeps its line breaks and whitespace, lines longer than `width` are split
+fn code_lines(code: &str, width: usize) -> Vec<Line> {
+    let mut lines = Vec::new();
+    for code_line in code.trim_end_matches('\n').split('\n') {
+        let mut line = Line::default();
+        for c in code_line.chars() {
+            if line.len() >= width {
+                lines.push(std::mem::take(&mut line));
+            }
+            line.push(c, Style::Code);
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+// Renders `html` as lines no longer than `width`, including `indent` columns before each
+// Paragraphs and code blocks are separated by a blank line
+pub fn render_html(html: &str, width: usize, indent: usize) -> Rendered {
+    let text_width = width.saturating_sub(indent).max(1);
+    let (blocks, _) = parse(html);
+    let mut lines = Vec::new();
+    for (i, block) in blocks.iter().enumerate() {
+        if i > 0 {
+            lines.push(Line::default());
+        }
+        let block_lines = match block {
+            Block::Paragraph(spans) => {
+                let is_quote = spans
+                    .iter()
+                    .map(|span| span.text.as_str())
+                    .collect::<String>()
+                    .trim_start()
+                    .starts_with('>');
+                if is_quote {
+                    let quoted: Vec<Span> = spans
+                        .iter()
+                        .map(|span| Span {
+                            text: span.text.clone(),
+                            style: match span.style {
+                                Style::Link(number) => Style::Link(number),
+                                _ => Style::Quote,
+                            },
+                        })
+                        .collect();
+                    wrap_spans(&quoted, text_width)
+                } else {
+                    wrap_spans(spans, text_width)
+                }
+            }
+            Block::Code(code) => code_lines(code, text_width),
+        };
+        lines.extend(block_lines);
+    }
+    for line in lines.iter_mut() {
+        line.indent = indent;
+    }
+    Rendered { lines }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(rendered: &Rendered) -> Vec<String> {
+        rendered.lines.iter().map(|line| line.text()).collect()
+    }
+
+    #[test]
+    fn decode_html_from_user_about() {
+        let about = "Bug fixer.<p>Email: pg&#x40;example.com &#x2F; I&#x27;m &quot;here&quot; &amp; <a href=\"https:&#x2F;&#x2F;paulgraham.com\">there</a> &foo";
+        assert_eq!(
+            decode_html(about),
+            "Bug fixer.\n\nEmail: pg@example.com / I'm \"here\" & there &foo"
+        );
+    }
+
+    #[test]
+    fn text_wraps_on_whitespace() {
+        let rendered = render_html("the quick brown fox jumps", 10, 0);
+        assert_eq!(texts(&rendered), vec!["the quick", "brown fox", "jumps"]);
+    }
+
+    #[test]
+    fn long_words_are_split() {
+        let rendered = render_html("abcdefghij klm", 4, 0);
+        assert_eq!(texts(&rendered), vec!["abcd", "efgh", "ij", "klm"]);
+    }
+
+    #[test]
+    fn bare_ampersands_keep_the_tags_after_them() {
+        let rendered = render_html("R&D <i>x</i>", 80, 0);
+        assert_eq!(
+            rendered.lines[0].spans,
+            vec![
+                Span {
+                    text: String::from("R&D "),
+                    style: Style::Plain,
+                },
+                Span {
+                    text: String::from("x"),
+                    style: Style::Italic,
+                },
+            ]
+        );
+        assert_eq!(decode_html("a & <i>b</i> &c<p>d"), "a & b &c\n\nd");
+    }
+
+    #[test]
+    fn paragraphs_italics_and_entities() {
+        let html = "It&#x27;s <i>really</i> not that hard.<p>Rust&#x27;s borrow checker &quot;just works&quot; once it clicks.";
+        let rendered = render_html(html, 24, 0);
+        assert_eq!(
+            texts(&rendered),
+            vec![
+                "It's really not that",
+                "hard.",
+                "",
+                "Rust's borrow checker",
+                "\"just works\" once it",
+                "clicks."
+            ]
+        );
+        let italic = &rendered.lines[0].spans[1];
+        assert_eq!(italic.text, "really");
+        assert_eq!(italic.style, Style::Italic);
+        assert!(extract_links(html).is_empty());
+    }
+
+    #[test]
+    fn links_are_numbered() {
+        let html = "See <a href=\"https:&#x2F;&#x2F;github.com&#x2F;HackerNews&#x2F;API\" rel=\"nofollow\">https:&#x2F;&#x2F;github.com&#x2F;HackerNews&#x2F;API</a> and <a href=\"item?id=8863\">this thread</a>.";
+        let rendered = render_html(html, 80, 0);
+        assert_eq!(
+            extract_links(html),
+            vec![
+                "https://github.com/HackerNews/API",
+                "https://news.ycombinator.com/item?id=8863"
+            ]
+        );
+        assert_eq!(
+            texts(&rendered),
+            vec!["See https://github.com/HackerNews/API [1] and this thread [2]."]
+        );
+        let spans = &rendered.lines[0].spans;
+        assert_eq!(spans[1].text, "https://github.com/HackerNews/API [1]");
+        assert_eq!(spans[1].style, Style::Link(1));
+        assert_eq!(spans[3].text, "this thread [2]");
+        assert_eq!(spans[3].style, Style::Link(2));
+    }
+
+    #[test]
+    fn code_blocks_are_preserved() {
+        let html = "Try this:<p><pre><code>  fn main() {\n      println!(&quot;hi&quot;);\n  }\n</code></pre>\nWorks for me.";
+        let rendered = render_html(html, 20, 0);
+        assert_eq!(
+            texts(&rendered),
+            vec![
+                "Try this:",
+                "",
+                "  fn main() {",
+                "      println!(\"hi\")",
+                ";",
+                "  }",
+                "",
+                "Works for me."
+            ]
+        );
+        assert_eq!(rendered.lines[2].spans[0].style, Style::Code);
+    }
+
+    #[test]
+    fn quotes_are_styled_and_lines_indented() {
+        let html = "&gt; I don&#x27;t think that&#x27;s true.<p>It is, see the <a href=\"https:&#x2F;&#x2F;example.com\">docs</a>.";
+        let rendered = render_html(html, 20, 4);
+        assert_eq!(
+            texts(&rendered),
+            vec![
+                "> I don't think",
+                "that's true.",
+                "",
+                "It is, see the",
+                "docs [1]."
+            ]
+        );
+        assert!(rendered.lines.iter().all(|line| line.indent == 4));
+        assert!(rendered.lines[..2]
+            .iter()
+            .flat_map(|line| line.spans.iter())
+            .all(|span| span.style == Style::Quote));
+    }
+}
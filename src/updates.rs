@@ -0,0 +1,266 @@
+use crate::backend::HnBackend;
+use crate::hn_client::{spawn_fetch, FetchHandle, HnClient, HnError};
+use crate::loader::Loaded;
+use crate::stores::data::{DataStore, Submission, User};
+use crate::stores::view::StoryListType;
+use futures::{Stream, StreamExt};
+use std::collections::HashSet;
+use std::sync::Arc;
+use tokio::sync::mpsc::UnboundedSender;
+
+// What had loaded when a check for updates started, used to pick the changes to load
+pub struct UpdateCheck {
+    item_ids: HashSet<u32>,
+    user_ids: HashSet<String>,
+    max_item: Option<u32>,
+    has_new_stories: bool,
+}
+
+impl UpdateCheck {
+    pub fn init(data_store: &DataStore) -> UpdateCheck {
+        UpdateCheck {
+            item_ids: data_store.get_loaded_item_ids(),
+            user_ids: data_store.get_loaded_user_ids(),
+            max_item: data_store.get_max_item(),
+            has_new_stories: data_store.has_post_ids(&StoryListType::New),
+        }
+    }
+}
+
+// Loaded data that has changed, see `get_changes`
+pub struct Changes {
+    items: Vec<Submission>,
+    users: Vec<User>,
+    max_item: Option<u32>,
+    // The new stories list, if items have been created since the last check
+    new_post_ids: Option<Vec<u32>>,
+}
+
+// Re-fetches the loaded items and users that the API lists as changed, and
+// reloads the new stories list if items have been created since the last check
+// Items and users that fail to load keep their previous data
+pub async fn get_changes(backend: &dyn HnBackend, check: UpdateCheck) -> Result<Changes, HnError> {
+    let updates = backend.get_updates().await?;
+
+    let changed_item_ids: Vec<u32> = updates
+        .items
+        .into_iter()
+        .filter(|item_id| check.item_ids.contains(item_id))
+        .collect();
+    let items = if changed_item_ids.is_empty() {
+        Vec::new()
+    } else {
+        backend.get_submissions(&changed_item_ids).await.items
+    };
+
+    let mut users = Vec::new();
+    for user_id in updates.profiles {
+        if !check.user_ids.contains(&user_id) {
+            continue;
+        }
+        if let Ok(user) = backend.get_user(&user_id).await {
+            users.push(user);
+        }
+    }
+
+    let max_item = backend.get_max_item().await?;
+    let has_new_items = match (check.max_item, max_item) {
+        (Some(previous), Some(max_item)) => max_item > previous,
+        _ => false,
+    };
+    let new_post_ids = if has_new_items && check.has_new_stories {
+        Some(backend.get_post_ids(&StoryListType::New).await?)
+    } else {
+        None
+    };
+
+    Ok(Changes {
+        items,
+        users,
+        max_item,
+        new_post_ids,
+    })
+}
+
+pub fn apply_changes(data_store: &mut DataStore, changes: Changes) {
+    data_store.hydrate_submissions(changes.items);
+    for user in changes.users {
+        data_store.hydrate_user(user);
+    }
+    if let Some(max_item) = changes.max_item {
+        data_store.hydrate_max_item(max_item);
+    }
+    if let Some(post_ids) = changes.new_post_ids {
+        data_store.hydrate_post_ids(&StoryListType::New, post_ids);
+    }
+}
+
+// Checks for updates in the background, sending the changes to `sender`
+pub fn load_updates(
+    backend: &Arc<dyn HnBackend>,
+    check: UpdateCheck,
+    sender: UnboundedSender<Loaded>,
+) -> FetchHandle {
+    let backend = backend.clone();
+    spawn_fetch(async move {
+        let changes = get_changes(backend.as_ref(), check).await;
+        let _ = sender.send(Loaded::Updates(changes));
+    })
+}
+
+// Sends changes to `sender` until cancelled
+fn spawn_forward<S>(changes: S, sender: UnboundedSender<Loaded>) -> FetchHandle
+where
+    S: Stream<Item = Loaded> + Send + 'static,
+{
+    spawn_fetch(async move {
+        let mut changes = Box::pin(changes);
+        while let Some(change) = changes.next().await {
+            if sender.send(change).is_err() {
+                break;
+            }
+        }
+    })
+}
+
+// Errors are skipped, the event stream reconnects by itself
+pub fn spawn_watch_post_ids(
+    hn_client: &HnClient,
+    story_type: StoryListType,
+    sender: UnboundedSender<Loaded>,
+) -> FetchHandle {
+    let changes = hn_client
+        .watch_post_ids(&story_type)
+        .filter_map(move |post_ids| async move {
+            post_ids
+                .ok()
+                .map(|post_ids| Loaded::PostIds(story_type, Ok(post_ids)))
+        });
+    spawn_forward(changes, sender)
+}
+
+pub fn spawn_watch_item(
+    hn_client: &HnClient,
+    id: u32,
+    sender: UnboundedSender<Loaded>,
+) -> FetchHandle {
+    let changes = hn_client
+        .watch_item(id)
+        .filter_map(|item| async move { item.ok().map(Loaded::Item) });
+    spawn_forward(changes, sender)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hn_client::HnClientConfig;
+    use crate::loader::apply_loaded;
+    use crate::stores::data::{Post, User};
+    use mockito::mock;
+    use tokio::sync::mpsc;
+
+    fn make_client() -> HnClient {
+        HnClient::init(HnClientConfig {
+            base_url: mockito::server_url(),
+            ..HnClientConfig::default()
+        })
+        .unwrap()
+    }
+
+    fn mock_json(route: &str, body: &str, expected_calls: usize) -> mockito::Mock {
+        mock("GET", route)
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(body)
+            .expect(expected_calls)
+            .create()
+    }
+
+    #[tokio::test]
+    async fn loaded_data_is_refreshed() {
+        let mut data_store = DataStore::init();
+        data_store.hydrate_posts(vec![Post {
+            id: 1,
+            by: String::from("pg"),
+            children: vec![],
+            title: String::from("title"),
+            time: 0,
+            url: None,
+            text: None,
+            descendants: 0,
+            score: 1,
+            dead: false,
+            deleted: false,
+        }]);
+        data_store.hydrate_user(User {
+            id: String::from("pg"),
+            created: 0,
+            karma: 1,
+            about: None,
+            submitted: vec![1],
+        });
+        data_store.hydrate_post_ids(&StoryListType::New, vec![1]);
+        data_store.hydrate_max_item(1);
+
+        let mocks = vec![
+            mock_json(
+                "/updates.json",
+                r#"{"items":[1,2],"profiles":["pg","dang"]}"#,
+                1,
+            ),
+            mock_json(
+                "/item/1.json",
+                r#"{"by":"pg","descendants":7,"id":1,"kids":[3],"score":100,"time":0,"title":"title","type":"story"}"#,
+                1,
+            ),
+            // Items and users that are not loaded are left alone
+            mock_json("/item/2.json", "null", 0),
+            mock_json(
+                "/user/pg.json",
+                r#"{"created":0,"id":"pg","karma":2,"submitted":[1]}"#,
+                1,
+            ),
+            mock_json("/user/dang.json", "null", 0),
+            mock_json("/maxitem.json", "3", 1),
+            mock_json("/newstories.json", "[3, 1]", 1),
+        ];
+
+        let check = UpdateCheck::init(&data_store);
+        let changes = get_changes(&make_client(), check).await.unwrap();
+        apply_changes(&mut data_store, changes);
+        for mock_inst in mocks {
+            mock_inst.assert();
+        }
+
+        let post = data_store.get_post(&1).unwrap();
+        assert_eq!(post.score, 100);
+        assert_eq!(post.descendants, 7);
+        assert_eq!(data_store.get_user("pg").unwrap().karma, 2);
+        assert_eq!(data_store.get_post_ids(&StoryListType::New), &[3, 1]);
+    }
+
+    #[tokio::test]
+    async fn story_list_is_kept_without_new_items() {
+        let mut data_store = DataStore::init();
+        data_store.hydrate_post_ids(&StoryListType::New, vec![1]);
+        data_store.hydrate_max_item(1);
+
+        let mocks = vec![
+            mock_json("/updates.json", r#"{"items":[],"profiles":[]}"#, 1),
+            mock_json("/maxitem.json", "1", 1),
+            mock_json("/newstories.json", "[3, 1]", 0),
+        ];
+
+        // Checks run in the background and send what changed back
+        let backend: Arc<dyn HnBackend> = Arc::new(make_client());
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        load_updates(&backend, UpdateCheck::init(&data_store), sender);
+        let loaded = receiver.recv().await.unwrap();
+        assert!(matches!(loaded, Loaded::Updates(Ok(_))));
+        apply_loaded(&mut data_store, loaded);
+        for mock_inst in mocks {
+            mock_inst.assert();
+        }
+        assert_eq!(data_store.get_post_ids(&StoryListType::New), &[1]);
+    }
+}